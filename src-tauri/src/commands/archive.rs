@@ -0,0 +1,980 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use super::fs::{symlink, FsConfig, FsConfigState};
+use super::operation::{CancelToken, OperationState, CANCELLED};
+
+// Progress events are throttled so huge trees don't flood the IPC channel
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub enum ArchiveFormat {
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ArchiveProgress {
+    pub operation_id: String,
+    pub entries: u64,
+    pub bytes: u64,
+    pub path: String,
+}
+
+/// Packs `source` into a new archive at `destination`, skipping ignored
+/// entries, and returns the archive's size in bytes. Files are streamed into
+/// the archive one at a time. Symlinks are stored as links, not followed.
+///
+/// Emits `archive-progress` while running and can be stopped with
+/// `cancel_operation(operation_id)`, in which case the partial archive is
+/// removed.
+#[tauri::command(async)]
+pub fn create_archive(
+    app_handle: AppHandle,
+    operations: tauri::State<'_, OperationState>,
+    config: tauri::State<'_, FsConfigState>,
+    source: String,
+    destination: String,
+    format: ArchiveFormat,
+    operation_id: String,
+) -> Result<u64, String> {
+    let operation = operations.start(&operation_id)?;
+    let token = operation.token();
+
+    let source_path = Path::new(&source)
+        .canonicalize()
+        .map_err(|_| format!("Directory does not exist: {}", source))?;
+    if !source_path.is_dir() {
+        return Err(format!("Path is not a directory: {}", source));
+    }
+
+    let file =
+        File::create(&destination).map_err(|e| format!("Failed to create archive: {}", e))?;
+    // The archive may live inside the tree being packed; never add it to itself
+    let destination_path = Path::new(&destination)
+        .canonicalize()
+        .map_err(|e| format!("Failed to create archive: {}", e))?;
+
+    let config = config.get();
+    let mut progress = Progress::new(app_handle, "archive-progress", operation_id);
+    let result = (|| {
+        let mut writer = ArchiveWriter::new(format, BufWriter::new(file));
+        add_tree(
+            &mut writer,
+            &source_path,
+            &source_path,
+            &destination_path,
+            &config,
+            &mut progress,
+            &token,
+        )?;
+        writer.finish()
+    })();
+
+    if let Err(e) = result {
+        let _ = fs::remove_file(&destination);
+        return Err(if token.is_cancelled() {
+            CANCELLED.to_string()
+        } else {
+            e
+        });
+    }
+
+    progress.finish();
+    fs::metadata(&destination)
+        .map(|m| m.len())
+        .map_err(|e| format!("Failed to read archive size: {}", e))
+}
+
+/// Unpacks a zip or tar.gz archive (detected from its magic bytes) into
+/// `destination`, overwriting existing files, and returns the extracted
+/// top-level entry names.
+///
+/// Every entry name is validated before anything is written: an absolute
+/// path or a `..` component anywhere in the archive rejects the whole
+/// archive (the "zip slip" attack), as does a symlink pointing outside the
+/// destination. Emits `extract-progress` and honours `cancel_operation`.
+#[tauri::command(async)]
+pub fn extract_archive(
+    app_handle: AppHandle,
+    operations: tauri::State<'_, OperationState>,
+    archive: String,
+    destination: String,
+    operation_id: String,
+) -> Result<Vec<String>, String> {
+    let operation = operations.start(&operation_id)?;
+    let token = operation.token();
+
+    let mut progress = Progress::new(app_handle, "extract-progress", operation_id);
+    let top_level = extract(
+        Path::new(&archive),
+        Path::new(&destination),
+        &token,
+        &mut |name, size| progress.entry(name, size),
+    )?;
+    progress.finish();
+
+    Ok(top_level)
+}
+
+/// Extracts only the named entries (paths as reported by `list_archive`)
+/// into `destination`; naming a directory extracts everything below it.
+/// The archive gets the same path checks as in `extract_archive`, and a
+/// name matching no entry fails the call before anything is written.
+/// Emits `extract-progress`, honours `cancel_operation`, and returns the
+/// paths of the files and symlinks written.
+#[tauri::command(async)]
+pub fn extract_archive_entries(
+    app_handle: AppHandle,
+    operations: tauri::State<'_, OperationState>,
+    archive: String,
+    destination: String,
+    entries: Vec<String>,
+    operation_id: String,
+) -> Result<Vec<String>, String> {
+    let operation = operations.start(&operation_id)?;
+    let token = operation.token();
+
+    let wanted = entries
+        .iter()
+        .map(|name| sanitize_entry_path(name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Every requested name must match something before anything is written,
+    // so a typo doesn't leave the other entries half extracted
+    let archive = Path::new(&archive);
+    let listed = read_entries(archive, detect_format(archive)?)?;
+    for (name, wanted) in entries.iter().zip(&wanted) {
+        if !listed.iter().any(|entry| entry.path.starts_with(wanted)) {
+            return Err(format!("Archive entry not found: {}", name));
+        }
+    }
+
+    let mut progress = Progress::new(app_handle, "extract-progress", operation_id);
+    let destination = Path::new(&destination);
+    let written = unpack(
+        archive,
+        destination,
+        &token,
+        &|path| wanted.iter().any(|wanted| path.starts_with(wanted)),
+        &mut |name, size| progress.entry(name, size),
+    )?;
+    progress.finish();
+
+    Ok(written
+        .iter()
+        .filter(|entry| matches!(entry.kind, EntryKind::File | EntryKind::Symlink))
+        .map(|entry| destination.join(&entry.path).to_string_lossy().to_string())
+        .collect())
+}
+
+fn extract(
+    archive: &Path,
+    destination: &Path,
+    token: &CancelToken,
+    on_entry: &mut dyn FnMut(&str, u64),
+) -> Result<Vec<String>, String> {
+    let written = unpack(archive, destination, token, &|_| true, on_entry)?;
+
+    let top_level: BTreeSet<String> = written
+        .iter()
+        .filter_map(|entry| entry.path.components().next())
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect();
+    Ok(top_level.into_iter().collect())
+}
+
+// Writes the entries whose sanitized path passes `select`, returning them
+fn unpack(
+    archive: &Path,
+    destination: &Path,
+    token: &CancelToken,
+    select: &dyn Fn(&Path) -> bool,
+    on_entry: &mut dyn FnMut(&str, u64),
+) -> Result<Vec<RawEntry>, String> {
+    let format = detect_format(archive)?;
+
+    // Validate up front so a malicious entry late in the archive can't leave
+    // a half-extracted tree behind. Unselected entries are checked too: an
+    // archive containing any unsafe entry isn't trusted at all
+    let entries = read_entries(archive, format)?;
+    for entry in &entries {
+        check_entry(entry)?;
+    }
+    check_link_parents(&entries)?;
+    let selected: Vec<bool> = entries.iter().map(|entry| select(&entry.path)).collect();
+
+    fs::create_dir_all(destination)
+        .map_err(|e| format!("Failed to create destination directory: {}", e))?;
+
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip = open_zip(archive)?;
+            for (index, entry) in entries.iter().enumerate() {
+                if !selected[index] {
+                    continue;
+                }
+                token.check()?;
+                let mut file = zip
+                    .by_index(index)
+                    .map_err(|e| format!("Failed to read archive: {}", e))?;
+                let target = destination.join(&entry.path);
+                let mode = file.unix_mode();
+                write_entry(entry, &target, &mut file, mode, token)?;
+                on_entry(&entry.name, entry.size);
+            }
+        }
+        ArchiveFormat::TarGz => {
+            let mut tar = open_tar(archive)?;
+            let files = tar
+                .entries()
+                .map_err(|e| format!("Failed to read archive: {}", e))?;
+            for ((file, entry), &selected) in files.zip(&entries).zip(&selected) {
+                // Tar is sequential, so skipped entries still have to be read past
+                let mut file = file.map_err(|e| format!("Failed to read archive: {}", e))?;
+                if !selected {
+                    continue;
+                }
+                token.check()?;
+                let target = destination.join(&entry.path);
+                let mode = file.header().mode().ok();
+                write_entry(entry, &target, &mut file, mode, token)?;
+                on_entry(&entry.name, entry.size);
+            }
+        }
+    }
+
+    Ok(entries
+        .into_iter()
+        .zip(selected)
+        .filter(|(_, selected)| *selected)
+        .map(|(entry, _)| entry)
+        .collect())
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveNode {
+    pub name: String,
+    /// Sanitized path inside the archive, `/`-separated
+    pub path: String,
+    pub size: u64,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    pub children: Vec<ArchiveNode>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveListing {
+    pub entries: Vec<ArchiveNode>,
+    /// Names that fail the path checks `extract_archive` applies (zip slip,
+    /// escaping symlinks); they're left out of the tree
+    pub rejected: Vec<String>,
+}
+
+/// Lists a zip or tar.gz archive as a tree without extracting anything.
+/// Directories the archive only implies (`a/b.txt` with no `a/` entry) are
+/// included. Siblings are sorted directories first, then by name.
+#[tauri::command(async)]
+pub fn list_archive(archive: String) -> Result<ArchiveListing, String> {
+    let archive = Path::new(&archive);
+    let entries = read_entries(archive, detect_format(archive)?)?;
+
+    let mut root = TreeBuilder::default();
+    let mut rejected = Vec::new();
+    for entry in &entries {
+        if check_entry(entry).is_err() {
+            rejected.push(entry.name.clone());
+            continue;
+        }
+        // Never extracted, so not worth showing
+        if entry.kind == EntryKind::Other {
+            continue;
+        }
+        let parts: Vec<String> = entry
+            .path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        root.insert(&parts, entry);
+    }
+
+    Ok(ArchiveListing {
+        entries: root.into_nodes(""),
+        rejected,
+    })
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    children: BTreeMap<String, TreeBuilder>,
+    size: u64,
+    is_dir: bool,
+    is_symlink: bool,
+}
+
+impl TreeBuilder {
+    fn insert(&mut self, parts: &[String], entry: &RawEntry) {
+        let Some((first, rest)) = parts.split_first() else {
+            return;
+        };
+        let node = self.children.entry(first.clone()).or_default();
+        if rest.is_empty() {
+            node.size = entry.size;
+            node.is_dir |= entry.kind == EntryKind::Dir;
+            node.is_symlink = entry.kind == EntryKind::Symlink;
+        } else {
+            node.is_dir = true;
+            node.insert(rest, entry);
+        }
+    }
+
+    fn into_nodes(self, parent: &str) -> Vec<ArchiveNode> {
+        let mut nodes: Vec<ArchiveNode> = self
+            .children
+            .into_iter()
+            .map(|(name, node)| {
+                let path = if parent.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}/{}", parent, name)
+                };
+                ArchiveNode {
+                    size: if node.is_dir { 0 } else { node.size },
+                    is_dir: node.is_dir,
+                    is_symlink: node.is_symlink,
+                    children: node.into_nodes(&path),
+                    name,
+                    path,
+                }
+            })
+            .collect();
+        nodes.sort_by_key(|node| !node.is_dir);
+        nodes
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    File,
+    Dir,
+    Symlink,
+    // Hard links, devices and the like are never extracted
+    Other,
+}
+
+struct RawEntry {
+    name: String,
+    path: PathBuf,
+    kind: EntryKind,
+    size: u64,
+    link_target: Option<String>,
+}
+
+fn detect_format(archive: &Path) -> Result<ArchiveFormat, String> {
+    let mut magic = [0u8; 4];
+    let read = File::open(archive)
+        .and_then(|mut f| f.read(&mut magic))
+        .map_err(|e| format!("Failed to open archive: {}", e))?;
+
+    match &magic[..read] {
+        [b'P', b'K', 3, 4] | [b'P', b'K', 5, 6] => Ok(ArchiveFormat::Zip),
+        [0x1f, 0x8b, ..] => Ok(ArchiveFormat::TarGz),
+        _ => Err(format!("Unsupported archive format: {}", archive.display())),
+    }
+}
+
+fn open_zip(archive: &Path) -> Result<ZipArchive<BufReader<File>>, String> {
+    let file = File::open(archive).map_err(|e| format!("Failed to open archive: {}", e))?;
+    ZipArchive::new(BufReader::new(file)).map_err(|e| format!("Failed to read archive: {}", e))
+}
+
+fn open_tar(
+    archive: &Path,
+) -> Result<tar::Archive<flate2::read::GzDecoder<BufReader<File>>>, String> {
+    let file = File::open(archive).map_err(|e| format!("Failed to open archive: {}", e))?;
+    Ok(tar::Archive::new(flate2::read::GzDecoder::new(
+        BufReader::new(file),
+    )))
+}
+
+// Lists entries with sanitized paths, in archive order. Entries with unsafe
+// names are kept (with an empty path) so check_entry can report them.
+fn read_entries(archive: &Path, format: ArchiveFormat) -> Result<Vec<RawEntry>, String> {
+    let mut entries = Vec::new();
+
+    match format {
+        ArchiveFormat::Zip => {
+            let mut zip = open_zip(archive)?;
+            for index in 0..zip.len() {
+                let mut file = zip
+                    .by_index(index)
+                    .map_err(|e| format!("Failed to read archive: {}", e))?;
+                let name = file.name().to_string();
+                let kind = if file.is_symlink() {
+                    EntryKind::Symlink
+                } else if file.is_dir() {
+                    EntryKind::Dir
+                } else if file.is_file() {
+                    EntryKind::File
+                } else {
+                    EntryKind::Other
+                };
+                // A zip symlink stores its target as the entry's content
+                let link_target = if kind == EntryKind::Symlink {
+                    let mut target = String::new();
+                    file.read_to_string(&mut target)
+                        .map_err(|e| format!("Failed to read archive: {}", e))?;
+                    Some(target)
+                } else {
+                    None
+                };
+                entries.push(RawEntry {
+                    path: sanitize_entry_path(&name).unwrap_or_default(),
+                    name,
+                    kind,
+                    size: file.size(),
+                    link_target,
+                });
+            }
+        }
+        ArchiveFormat::TarGz => {
+            let mut tar = open_tar(archive)?;
+            for file in tar
+                .entries()
+                .map_err(|e| format!("Failed to read archive: {}", e))?
+            {
+                let file = file.map_err(|e| format!("Failed to read archive: {}", e))?;
+                let name = String::from_utf8_lossy(&file.path_bytes()).to_string();
+                let header = file.header();
+                let kind = match header.entry_type() {
+                    tar::EntryType::Regular | tar::EntryType::Continuous => EntryKind::File,
+                    tar::EntryType::Directory => EntryKind::Dir,
+                    tar::EntryType::Symlink => EntryKind::Symlink,
+                    _ => EntryKind::Other,
+                };
+                let link_target = file
+                    .link_name_bytes()
+                    .map(|target| String::from_utf8_lossy(&target).to_string());
+                entries.push(RawEntry {
+                    path: sanitize_entry_path(&name).unwrap_or_default(),
+                    name,
+                    kind,
+                    size: header.size().unwrap_or(0),
+                    link_target,
+                });
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+fn check_entry(entry: &RawEntry) -> Result<(), String> {
+    sanitize_entry_path(&entry.name)?;
+
+    if entry.kind == EntryKind::Symlink {
+        let target = entry.link_target.as_deref().unwrap_or_default();
+        // Resolve the target lexically relative to the link's own directory;
+        // it must not climb out of the destination
+        let base = entry.path.parent().unwrap_or(Path::new(""));
+        let mut depth = base.components().count() as isize;
+        for component in Path::new(&target.replace('\\', "/")).components() {
+            match component {
+                Component::Normal(_) => depth += 1,
+                Component::CurDir => {}
+                Component::ParentDir => depth -= 1,
+                Component::RootDir | Component::Prefix(_) => depth = -1,
+            }
+            if depth < 0 {
+                return Err(format!(
+                    "Archive symlink points outside the destination: {} -> {}",
+                    entry.name, target
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Each link target is checked on its own above, so links chained through
+// one another (`l -> ..`, then `l/x -> ../..`) could still climb out. No
+// entry may be written through a link the archive itself creates
+fn check_link_parents(entries: &[RawEntry]) -> Result<(), String> {
+    let links: BTreeSet<&Path> = entries
+        .iter()
+        .filter(|entry| entry.kind == EntryKind::Symlink)
+        .map(|entry| entry.path.as_path())
+        .collect();
+    if links.is_empty() {
+        return Ok(());
+    }
+    for entry in entries {
+        if entry
+            .path
+            .ancestors()
+            .skip(1)
+            .any(|parent| links.contains(parent))
+        {
+            return Err(format!("Archive entry is inside a symlink: {}", entry.name));
+        }
+    }
+    Ok(())
+}
+
+/// Turns an archive entry name into a relative path that can't escape the
+/// extraction directory. Absolute paths, drive prefixes and any `..`
+/// component are rejected outright rather than stripped.
+pub(crate) fn sanitize_entry_path(name: &str) -> Result<PathBuf, String> {
+    let unsafe_entry = || format!("Archive entry has an unsafe path: {}", name);
+
+    // Treat backslashes as separators too, so Windows-made archives can't
+    // smuggle `..\` past the check
+    let normalized = name.replace('\\', "/");
+    let mut path = PathBuf::new();
+    for component in Path::new(&normalized).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(unsafe_entry());
+            }
+        }
+    }
+
+    // `C:foo` has no separator but is still drive-relative on Windows
+    if normalized.starts_with('/') || normalized.get(1..2) == Some(":") {
+        return Err(unsafe_entry());
+    }
+    if path.as_os_str().is_empty() {
+        return Err(unsafe_entry());
+    }
+
+    Ok(path)
+}
+
+fn write_entry(
+    entry: &RawEntry,
+    target: &Path,
+    content: &mut dyn Read,
+    mode: Option<u32>,
+    token: &CancelToken,
+) -> Result<(), String> {
+    let err = |e: &dyn std::fmt::Display| format!("Failed to extract {}: {}", entry.name, e);
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| err(&e))?;
+    }
+
+    match entry.kind {
+        EntryKind::Dir => fs::create_dir_all(target).map_err(|e| err(&e))?,
+        EntryKind::File => {
+            let mut file = File::create(target).map_err(|e| err(&e))?;
+            let mut reader = CancellableReader {
+                inner: content,
+                token: token.clone(),
+            };
+            io::copy(&mut reader, &mut file).map_err(|e| err(&e))?;
+            #[cfg(unix)]
+            if let Some(mode) = mode {
+                use std::os::unix::fs::PermissionsExt;
+                let _ = file.set_permissions(fs::Permissions::from_mode(mode & 0o777));
+            }
+        }
+        EntryKind::Symlink => {
+            let link_target = entry.link_target.as_deref().unwrap_or_default();
+            let _ = fs::remove_file(target);
+            symlink(link_target, target).map_err(|e| err(&e))?;
+        }
+        EntryKind::Other => {}
+    }
+
+    #[cfg(not(unix))]
+    let _ = mode;
+    Ok(())
+}
+
+fn add_tree(
+    writer: &mut ArchiveWriter,
+    root: &Path,
+    dir: &Path,
+    skip: &Path,
+    config: &FsConfig,
+    progress: &mut Progress,
+    token: &CancelToken,
+) -> Result<(), String> {
+    let mut entries: Vec<_> = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read directory: {}", e))?
+        .flatten()
+        .collect();
+    // Sorted so the same tree always produces the same archive layout
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        token.check()?;
+
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        if config.is_ignored(&file_name) || path == skip {
+            continue;
+        }
+
+        let name = archive_name(root, &path);
+        let file_type = entry
+            .file_type()
+            .map_err(|e| format!("Failed to read {}: {}", name, e))?;
+
+        let size = if file_type.is_symlink() {
+            let target =
+                fs::read_link(&path).map_err(|e| format!("Failed to read {}: {}", name, e))?;
+            writer.add_symlink(&name, &target)?;
+            0
+        } else if file_type.is_dir() {
+            writer.add_dir(&name, &path)?;
+            add_tree(writer, root, &path, skip, config, progress, token)?;
+            0
+        } else if file_type.is_file() {
+            writer.add_file(&name, &path, token)?
+        } else {
+            // Sockets, FIFOs and devices have no meaningful archive content
+            continue;
+        };
+
+        progress.entry(&name, size);
+    }
+
+    Ok(())
+}
+
+// Archive entry names always use forward slashes, whatever the platform
+fn archive_name(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+enum ArchiveWriter {
+    Zip(ZipWriter<BufWriter<File>>),
+    TarGz(tar::Builder<GzEncoder<BufWriter<File>>>),
+}
+
+impl ArchiveWriter {
+    fn new(format: ArchiveFormat, out: BufWriter<File>) -> Self {
+        match format {
+            ArchiveFormat::Zip => Self::Zip(ZipWriter::new(out)),
+            ArchiveFormat::TarGz => {
+                let mut builder = tar::Builder::new(GzEncoder::new(out, Compression::default()));
+                builder.follow_symlinks(false);
+                Self::TarGz(builder)
+            }
+        }
+    }
+
+    fn add_dir(&mut self, name: &str, path: &Path) -> Result<(), String> {
+        let err = |e: &dyn std::fmt::Display| format!("Failed to add {}: {}", name, e);
+        match self {
+            Self::Zip(zip) => {
+                let options = zip_options(&fs::metadata(path).map_err(|e| err(&e))?);
+                zip.add_directory(format!("{}/", name), options)
+                    .map_err(|e| err(&e))
+            }
+            Self::TarGz(tar) => tar.append_dir(name, path).map_err(|e| err(&e)),
+        }
+    }
+
+    fn add_file(&mut self, name: &str, path: &Path, token: &CancelToken) -> Result<u64, String> {
+        let err = |e: &dyn std::fmt::Display| format!("Failed to add {}: {}", name, e);
+        let file = File::open(path).map_err(|e| err(&e))?;
+        let metadata = file.metadata().map_err(|e| err(&e))?;
+        let mut reader = CancellableReader {
+            inner: file,
+            token: token.clone(),
+        };
+
+        match self {
+            Self::Zip(zip) => {
+                let options = zip_options(&metadata)
+                    .compression_method(CompressionMethod::Deflated)
+                    .large_file(metadata.len() >= u32::MAX as u64);
+                zip.start_file(name, options).map_err(|e| err(&e))?;
+                io::copy(&mut reader, zip).map_err(|e| err(&e))?;
+            }
+            Self::TarGz(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_metadata(&metadata);
+                tar.append_data(&mut header, name, reader)
+                    .map_err(|e| err(&e))?;
+            }
+        }
+
+        Ok(metadata.len())
+    }
+
+    fn add_symlink(&mut self, name: &str, target: &Path) -> Result<(), String> {
+        let err = |e: &dyn std::fmt::Display| format!("Failed to add {}: {}", name, e);
+        match self {
+            Self::Zip(zip) => zip
+                .add_symlink(name, target.to_string_lossy(), SimpleFileOptions::default())
+                .map_err(|e| err(&e)),
+            Self::TarGz(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_entry_type(tar::EntryType::Symlink);
+                header.set_size(0);
+                header.set_mode(0o777);
+                tar.append_link(&mut header, name, target)
+                    .map_err(|e| err(&e))
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        let err = |e: &dyn std::fmt::Display| format!("Failed to finish archive: {}", e);
+        let out = match self {
+            Self::Zip(zip) => zip.finish().map_err(|e| err(&e))?,
+            Self::TarGz(tar) => tar
+                .into_inner()
+                .and_then(|gz| gz.finish())
+                .map_err(|e| err(&e))?,
+        };
+        out.into_inner()
+            .map_err(|e| err(&e.into_error()))?
+            .sync_all()
+            .map_err(|e| err(&e))
+    }
+}
+
+fn zip_options(metadata: &fs::Metadata) -> SimpleFileOptions {
+    let options = SimpleFileOptions::default();
+    #[cfg(unix)]
+    let options = {
+        use std::os::unix::fs::PermissionsExt;
+        options.unix_permissions(metadata.permissions().mode())
+    };
+    #[cfg(not(unix))]
+    let _ = metadata;
+    options
+}
+
+// Lets a cancellation interrupt a single large file instead of waiting for
+// the whole copy to finish. Not `Interrupted`: `io::copy` and tar retry on
+// that, which would spin forever
+struct CancellableReader<R> {
+    inner: R,
+    token: CancelToken,
+}
+
+impl<R: Read> Read for CancellableReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.token.is_cancelled() {
+            return Err(io::Error::other(CANCELLED));
+        }
+        self.inner.read(buf)
+    }
+}
+
+struct Progress {
+    app_handle: AppHandle,
+    event: &'static str,
+    operation_id: String,
+    entries: u64,
+    bytes: u64,
+    path: String,
+    last_emit: Instant,
+}
+
+impl Progress {
+    fn new(app_handle: AppHandle, event: &'static str, operation_id: String) -> Self {
+        Self {
+            app_handle,
+            event,
+            operation_id,
+            entries: 0,
+            bytes: 0,
+            path: String::new(),
+            last_emit: Instant::now(),
+        }
+    }
+
+    fn entry(&mut self, path: &str, size: u64) {
+        self.entries += 1;
+        self.bytes += size;
+        if self.last_emit.elapsed() >= PROGRESS_INTERVAL {
+            self.path = path.to_string();
+            self.emit();
+        }
+    }
+
+    fn finish(&mut self) {
+        self.emit();
+    }
+
+    fn emit(&mut self) {
+        self.last_emit = Instant::now();
+        let _ = self.app_handle.emit(
+            self.event,
+            ArchiveProgress {
+                operation_id: self.operation_id.clone(),
+                entries: self.entries,
+                bytes: self.bytes,
+                path: self.path.clone(),
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_util::TempDir;
+    use std::io::Write;
+
+    fn write_zip(path: &Path, entries: &[(&str, &str)]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn extract_to(archive: &Path, destination: &Path) -> Result<Vec<String>, String> {
+        let operations = OperationState::new();
+        let operation = operations.start("test").unwrap();
+        extract(archive, destination, &operation.token(), &mut |_, _| {})
+    }
+
+    #[test]
+    fn extract_archive_rejects_parent_traversal() {
+        let dir = TempDir::new("slip");
+        let archive = dir.join("evil.zip");
+        write_zip(&archive, &[("ok.txt", "fine"), ("../evil.txt", "pwned")]);
+
+        let err = extract_to(&archive, &dir.join("out")).unwrap_err();
+
+        assert!(err.contains("unsafe path"), "{}", err);
+        assert!(!dir.join("evil.txt").exists());
+        // Nothing is written when any entry is rejected
+        assert!(!dir.join("out/ok.txt").exists());
+    }
+
+    #[test]
+    fn extract_archive_rejects_absolute_paths() {
+        let dir = TempDir::new("absolute");
+        let archive = dir.join("evil.zip");
+        write_zip(&archive, &[("/tmp/evil.txt", "pwned")]);
+
+        assert!(extract_to(&archive, &dir.join("out")).is_err());
+        assert!(sanitize_entry_path("C:\\Windows\\evil.dll").is_err());
+        assert!(sanitize_entry_path("a\\..\\..\\evil").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_archive_rejects_entries_through_archive_links() {
+        let dir = TempDir::new("chained-links");
+        let archive = dir.join("evil.tar.gz");
+        let mut tar = tar::Builder::new(GzEncoder::new(
+            File::create(&archive).unwrap(),
+            Compression::default(),
+        ));
+        let mut link = |path: &str, target: &str| {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            tar.append_link(&mut header, path, target).unwrap();
+        };
+        link("deep/deep2/l", "../..");
+        link("deep/deep2/l/x", "../../..");
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, "deep/deep2/l/x/evil", "pwned".as_bytes())
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let out = dir.join("nested/out");
+        let err = extract_to(&archive, &out).unwrap_err();
+
+        assert!(err.contains("inside a symlink"), "{}", err);
+        assert!(!dir.join("evil").exists());
+        assert!(!out.join("deep").exists());
+    }
+
+    // Endless content that cancels the operation on its first read, standing
+    // in for an entry too large to finish before the cancel arrives
+    struct CancelOnRead<'a> {
+        operations: &'a OperationState,
+    }
+
+    impl Read for CancelOnRead<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.operations.cancel("test");
+            buf.fill(0);
+            Ok(buf.len())
+        }
+    }
+
+    #[test]
+    fn write_entry_stops_when_cancelled_mid_file() {
+        let dir = TempDir::new("cancel");
+        let operations = OperationState::new();
+        let operation = operations.start("test").unwrap();
+        let entry = RawEntry {
+            name: "big.bin".to_string(),
+            path: PathBuf::from("big.bin"),
+            kind: EntryKind::File,
+            size: u64::MAX,
+            link_target: None,
+        };
+        let mut content = CancelOnRead {
+            operations: &operations,
+        };
+
+        let err = write_entry(
+            &entry,
+            &dir.join("big.bin"),
+            &mut content,
+            None,
+            &operation.token(),
+        )
+        .unwrap_err();
+
+        assert!(err.contains(CANCELLED), "{}", err);
+    }
+
+    #[test]
+    fn extract_archive_reports_top_level_entries() {
+        let dir = TempDir::new("top-level");
+        let archive = dir.join("ok.zip");
+        write_zip(
+            &archive,
+            &[
+                ("src/main.rs", "fn main() {}"),
+                ("src/lib.rs", ""),
+                ("README.md", "hi"),
+            ],
+        );
+
+        let top_level = extract_to(&archive, &dir.join("out")).unwrap();
+
+        assert_eq!(top_level, vec!["README.md", "src"]);
+        assert_eq!(
+            fs::read_to_string(dir.join("out/src/main.rs")).unwrap(),
+            "fn main() {}"
+        );
+    }
+}
@@ -0,0 +1,408 @@
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+use std::fs;
+use std::path::Path;
+
+use super::fs::{looks_binary, write_atomic, FileLockState, MAX_TEXT_FILE_SIZE};
+
+// Lines of unchanged context kept around each hunk, as in `diff -u`
+const CONTEXT_LINES: usize = 3;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum FileDiff {
+    Text { hunks: Vec<DiffHunk> },
+    BinaryDiff,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffHunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    pub lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffLineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffLine {
+    pub kind: DiffLineKind,
+    pub content: String,
+    /// 1-based line number in the old file, absent for added lines
+    pub old_line: Option<usize>,
+    /// 1-based line number in the new file, absent for removed lines
+    pub new_line: Option<usize>,
+}
+
+#[tauri::command]
+pub fn diff_files(old_path: &str, new_path: &str) -> Result<FileDiff, String> {
+    let old = read_diffable(old_path)?;
+    let new = read_diffable(new_path)?;
+
+    match (old, new) {
+        (Some(old), Some(new)) => Ok(FileDiff::Text {
+            hunks: diff_text(&old, &new),
+        }),
+        _ => Ok(FileDiff::BinaryDiff),
+    }
+}
+
+// Returns None when the file is binary (or not valid UTF-8)
+fn read_diffable(path: &str) -> Result<Option<String>, String> {
+    let file_path = Path::new(path);
+
+    if !file_path.is_file() {
+        return Err(format!("Path is not a file: {}", path));
+    }
+
+    let size = fs::metadata(file_path)
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?
+        .len();
+    if size > MAX_TEXT_FILE_SIZE {
+        return Err(format!(
+            "File is too large to diff ({} bytes, limit is {}): {}",
+            size, MAX_TEXT_FILE_SIZE, path
+        ));
+    }
+
+    let bytes = fs::read(file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    if looks_binary(&bytes) {
+        return Ok(None);
+    }
+
+    Ok(String::from_utf8(bytes).ok())
+}
+
+pub(crate) fn diff_text(old: &str, new: &str) -> Vec<DiffHunk> {
+    let diff = TextDiff::from_lines(old, new);
+
+    diff.grouped_ops(CONTEXT_LINES)
+        .iter()
+        .filter_map(|group| {
+            let first = group.first()?;
+            let last = group.last()?;
+            let old_range = first.old_range().start..last.old_range().end;
+            let new_range = first.new_range().start..last.new_range().end;
+
+            let lines = group
+                .iter()
+                .flat_map(|op| diff.iter_changes(op))
+                .map(|change| DiffLine {
+                    kind: match change.tag() {
+                        ChangeTag::Equal => DiffLineKind::Context,
+                        ChangeTag::Insert => DiffLineKind::Added,
+                        ChangeTag::Delete => DiffLineKind::Removed,
+                    },
+                    content: change.value().trim_end_matches(['\n', '\r']).to_string(),
+                    old_line: change.old_index().map(|i| i + 1),
+                    new_line: change.new_index().map(|i| i + 1),
+                })
+                .collect();
+
+            Some(DiffHunk {
+                old_start: old_range.start + 1,
+                old_lines: old_range.len(),
+                new_start: new_range.start + 1,
+                new_lines: new_range.len(),
+                lines,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct PatchResult {
+    pub hunks: usize,
+    /// False for a dry run
+    pub written: bool,
+}
+
+/// Applies a single-file unified diff to `path` and writes the result
+/// atomically. Hunks must match exactly apart from line endings, but may
+/// have moved up or down the file, as with `patch`. On the first hunk that
+/// doesn't match, nothing is written and the error names the hunk. With
+/// `dry_run` the patch is only checked.
+#[tauri::command]
+pub fn apply_patch(
+    locks: tauri::State<'_, FileLockState>,
+    path: &str,
+    patch: &str,
+    dry_run: Option<bool>,
+) -> Result<PatchResult, String> {
+    let parsed = parse_patch(patch)?;
+
+    // A patch from /dev/null creates the file
+    let original = if parsed.creates_file && !Path::new(path).exists() {
+        String::new()
+    } else {
+        read_diffable(path)?.ok_or_else(|| format!("Cannot patch a binary file: {}", path))?
+    };
+
+    let (bom, body) = match original.strip_prefix('\u{feff}') {
+        Some(body) => ("\u{feff}", body),
+        None => ("", original.as_str()),
+    };
+    let patched = format!("{}{}", bom, apply_hunks(body, &parsed.hunks)?);
+
+    let dry_run = dry_run.unwrap_or(false);
+    if !dry_run {
+        locks.ensure_unlocked(Path::new(path))?;
+        write_atomic(Path::new(path), patched.as_bytes(), false)?;
+    }
+    Ok(PatchResult {
+        hunks: parsed.hunks.len(),
+        written: !dry_run,
+    })
+}
+
+struct Patch {
+    creates_file: bool,
+    hunks: Vec<Hunk>,
+}
+
+struct Hunk {
+    header: String,
+    old_start: usize,
+    // Line text without its ending, and whether the line has one
+    old: Vec<(String, bool)>,
+    new: Vec<(String, bool)>,
+}
+
+fn parse_patch(patch: &str) -> Result<Patch, String> {
+    let mut creates_file = false;
+    let mut files = 0;
+    let mut hunks: Vec<Hunk> = Vec::new();
+    let mut lines = patch.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        if let Some(old_name) = line.strip_prefix("--- ") {
+            files += 1;
+            if files > 1 {
+                return Err("Patch changes more than one file".to_string());
+            }
+            creates_file = old_name.split('\t').next() == Some("/dev/null");
+            continue;
+        }
+        // Anything else outside a hunk is a header (`diff`, `index`, `+++`)
+        // or commentary
+        let Some(range) = line.strip_prefix("@@ ") else {
+            continue;
+        };
+
+        let header = line.to_string();
+        let invalid = || format!("Invalid hunk header: {}", header);
+        let mut parts = range.split_whitespace();
+        let (old_start, old_count) = parts
+            .next()
+            .and_then(|part| part.strip_prefix('-'))
+            .and_then(parse_range)
+            .ok_or_else(invalid)?;
+        let (_, new_count) = parts
+            .next()
+            .and_then(|part| part.strip_prefix('+'))
+            .and_then(parse_range)
+            .ok_or_else(invalid)?;
+
+        let mut hunk = Hunk {
+            header: header.clone(),
+            old_start,
+            old: Vec::new(),
+            new: Vec::new(),
+        };
+        while hunk.old.len() < old_count || hunk.new.len() < new_count {
+            let Some(line) = lines.next() else {
+                return Err(format!("Hunk {} is truncated: {}", hunks.len() + 1, header));
+            };
+            // Some editors strip the lone space from empty context lines
+            let (marker, text) = match line.chars().next() {
+                Some(marker) => (marker, &line[marker.len_utf8()..]),
+                None => (' ', ""),
+            };
+            let entry = (text.to_string(), true);
+            match marker {
+                ' ' => {
+                    hunk.old.push(entry.clone());
+                    hunk.new.push(entry);
+                }
+                '-' => hunk.old.push(entry),
+                '+' => hunk.new.push(entry),
+                _ => {
+                    return Err(format!(
+                        "Unexpected line in hunk {}: {}",
+                        hunks.len() + 1,
+                        line
+                    ))
+                }
+            }
+            mark_missing_newline(&mut lines, marker, &mut hunk);
+        }
+        if hunk.old.len() != old_count || hunk.new.len() != new_count {
+            return Err(format!(
+                "Hunk {} doesn't match its line counts: {}",
+                hunks.len() + 1,
+                header
+            ));
+        }
+        hunks.push(hunk);
+    }
+
+    if hunks.is_empty() {
+        return Err("Patch contains no hunks".to_string());
+    }
+    Ok(Patch {
+        creates_file,
+        hunks,
+    })
+}
+
+// `\ No newline at end of file` applies to the line just read
+fn mark_missing_newline<'a>(
+    lines: &mut std::iter::Peekable<impl Iterator<Item = &'a str>>,
+    marker: char,
+    hunk: &mut Hunk,
+) {
+    if !lines.peek().is_some_and(|next| next.starts_with('\\')) {
+        return;
+    }
+    lines.next();
+    if matches!(marker, ' ' | '-') {
+        if let Some(last) = hunk.old.last_mut() {
+            last.1 = false;
+        }
+    }
+    if matches!(marker, ' ' | '+') {
+        if let Some(last) = hunk.new.last_mut() {
+            last.1 = false;
+        }
+    }
+}
+
+// `start,count` or just `start`, which means a count of one
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+fn apply_hunks(content: &str, hunks: &[Hunk]) -> Result<String, String> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let eol = if content.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    };
+    let text = |line: &str| line.trim_end_matches(['\n', '\r']).to_string();
+
+    let mut output = String::with_capacity(content.len());
+    // Next unconsumed line of the original
+    let mut cursor = 0;
+    // How far hunks so far were found from where their headers put them
+    let mut offset: isize = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        // A pure insertion's start is the line it goes after
+        let stated = if hunk.old.is_empty() {
+            hunk.old_start
+        } else {
+            hunk.old_start.saturating_sub(1)
+        };
+        let expected = (stated as isize + offset).max(cursor as isize) as usize;
+
+        let matches_at = |at: usize| {
+            at + hunk.old.len() <= lines.len()
+                && hunk
+                    .old
+                    .iter()
+                    .zip(&lines[at..])
+                    .all(|((old, _), line)| *old == text(line))
+        };
+        // Search outward from the expected position, never before the end of
+        // the previous hunk
+        let found = (0..=lines.len()).find_map(|distance| {
+            let below = expected + distance;
+            if matches_at(below) {
+                return Some(below);
+            }
+            let above = expected.checked_sub(distance).filter(|&at| at >= cursor)?;
+            matches_at(above).then_some(above)
+        });
+        let Some(at) = found else {
+            return Err(format!(
+                "Hunk {} failed to apply, context doesn't match: {}",
+                index + 1,
+                hunk.header
+            ));
+        };
+
+        lines[cursor..at]
+            .iter()
+            .for_each(|line| output.push_str(line));
+        for (line, newline) in &hunk.new {
+            output.push_str(line);
+            if *newline {
+                output.push_str(eol);
+            }
+        }
+        cursor = at + hunk.old.len();
+        offset = at as isize - stated as isize;
+    }
+
+    lines[cursor..]
+        .iter()
+        .for_each(|line| output.push_str(line));
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(content: &str, patch: &str) -> Result<String, String> {
+        apply_hunks(content, &parse_patch(patch)?.hunks)
+    }
+
+    #[test]
+    fn applies_hunks_with_offset_and_missing_newline() {
+        let patch = "--- a/f\n+++ b/f\n@@ -2,2 +2,2 @@\n b\n-c\n+C\n@@ -5 +5,2 @@\n-e\n\\ No newline at end of file\n+E\n+f\n";
+        assert_eq!(apply("a\nb\nc\nd\ne", patch).unwrap(), "a\nb\nC\nd\nE\nf\n");
+        // Two extra lines at the top shift both hunks
+        assert_eq!(
+            apply("x\ny\na\nb\nc\nd\ne", patch).unwrap(),
+            "x\ny\na\nb\nC\nd\nE\nf\n"
+        );
+    }
+
+    #[test]
+    fn keeps_crlf_and_reports_failed_hunk() {
+        let patch = "@@ -1,2 +1,2 @@\n a\n-b\n+B\n@@ -4 +4 @@\n-z\n+Z\n";
+        assert_eq!(
+            apply("a\r\nb\r\nc\r\nd\r\nz\r\n", patch).unwrap(),
+            "a\r\nB\r\nc\r\nd\r\nZ\r\n"
+        );
+        let error = apply("a\nb\nc\nd\n", patch).unwrap_err();
+        assert!(error.starts_with("Hunk 2 failed to apply"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_hunk_line_starting_with_multibyte_char() {
+        let patch = "--- a/f\n+++ b/f\n@@ -1 +1 @@\né\n";
+        let err = parse_patch(patch).err().unwrap();
+        assert!(err.contains("Unexpected line"), "{}", err);
+    }
+
+    #[test]
+    fn inserts_into_empty_file() {
+        let patch = "--- /dev/null\n+++ b/new\n@@ -0,0 +1,2 @@\n+one\n+two\n";
+        assert!(parse_patch(patch).unwrap().creates_file);
+        assert_eq!(apply("", patch).unwrap(), "one\ntwo\n");
+    }
+}
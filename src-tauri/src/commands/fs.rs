@@ -1,6 +1,25 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use fs2::FileExt;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::io::{BufRead, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use super::operation::{CancelToken, OperationState};
+
+/// Largest file the text-oriented commands will load into memory.
+pub const MAX_TEXT_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// How many leading bytes are inspected when guessing whether content is binary.
+pub(crate) const BINARY_SNIFF_LEN: usize = 8 * 1024;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileEntry {
@@ -8,10 +27,407 @@ pub struct FileEntry {
     pub path: String,
     pub is_dir: bool,
     pub is_hidden: bool,
+    /// What the entry (or a symlink's target) is, so the tree only opens
+    /// regular files
+    pub kind: FileKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileKind {
+    File,
+    Dir,
+    Fifo,
+    Socket,
+    CharDevice,
+    BlockDevice,
+    /// Dangling symlinks and anything else unrecognised
+    Other,
+}
+
+impl FileKind {
+    fn of(file_type: fs::FileType) -> Self {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::FileTypeExt;
+            if file_type.is_fifo() {
+                return FileKind::Fifo;
+            }
+            if file_type.is_socket() {
+                return FileKind::Socket;
+            }
+            if file_type.is_char_device() {
+                return FileKind::CharDevice;
+            }
+            if file_type.is_block_device() {
+                return FileKind::BlockDevice;
+            }
+        }
+        if file_type.is_dir() {
+            FileKind::Dir
+        } else if file_type.is_file() {
+            FileKind::File
+        } else {
+            FileKind::Other
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            FileKind::File => "file",
+            FileKind::Dir => "directory",
+            FileKind::Fifo => "named pipe",
+            FileKind::Socket => "socket",
+            FileKind::CharDevice => "character device",
+            FileKind::BlockDevice => "block device",
+            FileKind::Other => "special file",
+        }
+    }
+}
+
+// Checked before opening a file to read it whole: opening a FIFO blocks
+// until a writer shows up and a device like /dev/zero never ends
+fn ensure_regular_file(path: &str) -> Result<(), String> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(format!("File does not exist: {}", path));
+        }
+        Err(e) => return Err(format!("Failed to read file metadata: {}", e)),
+    };
+
+    match FileKind::of(metadata.file_type()) {
+        FileKind::File => Ok(()),
+        FileKind::Dir => Err(format!("Path is not a file: {}", path)),
+        kind => Err(format!(
+            "Not a regular file ({}): {}",
+            kind.describe(),
+            path
+        )),
+    }
+}
+
+// Skip certain directories/files unless the frontend configures otherwise
+const DEFAULT_IGNORE: &[&str] = &["node_modules", ".git", "target", ".DS_Store"];
+
+/// File-tree preferences the frontend sets once via `set_fs_config` rather
+/// than passing them to every listing call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FsConfig {
+    /// File names, or `*`/`?` globs matched against file names
+    pub ignore: Vec<String>,
+    pub show_hidden: bool,
+    /// Whether recursive walks (`search_in_files`, `recent_files`) descend
+    /// into symlinked directories and include symlinked files. Each
+    /// directory is still walked once, so a link back up the tree can't
+    /// loop. Directory listings are unaffected: they always report a link
+    /// by what it points at.
+    pub follow_symlinks: bool,
+}
+
+impl Default for FsConfig {
+    fn default() -> Self {
+        Self {
+            ignore: DEFAULT_IGNORE.iter().map(|name| name.to_string()).collect(),
+            show_hidden: true,
+            follow_symlinks: false,
+        }
+    }
+}
+
+impl FsConfig {
+    pub(crate) fn is_ignored(&self, file_name: &str) -> bool {
+        self.ignore
+            .iter()
+            .any(|pattern| glob_match(pattern, file_name))
+    }
+}
+
+pub struct FsConfigState {
+    config: Mutex<FsConfig>,
+}
+
+impl FsConfigState {
+    pub fn new() -> Self {
+        Self {
+            config: Mutex::new(FsConfig::default()),
+        }
+    }
+
+    pub(crate) fn get(&self) -> FsConfig {
+        self.config.lock().clone()
+    }
+
+    // The stored config for a recursive walk, with the per-call symlink
+    // override applied
+    pub(crate) fn for_walk(&self, follow_symlinks: Option<bool>) -> FsConfig {
+        let mut config = self.get();
+        if let Some(follow_symlinks) = follow_symlinks {
+            config.follow_symlinks = follow_symlinks;
+        }
+        config
+    }
+
+    // The stored config with any per-call overrides applied
+    fn resolve(&self, ignore: Option<Vec<String>>, show_hidden: Option<bool>) -> FsConfig {
+        let mut config = self.get();
+        if let Some(ignore) = ignore {
+            config.ignore = ignore;
+        }
+        if let Some(show_hidden) = show_hidden {
+            config.show_hidden = show_hidden;
+        }
+        config
+    }
+}
+
+impl Default for FsConfigState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[tauri::command]
+pub fn get_fs_config(state: tauri::State<'_, FsConfigState>) -> FsConfig {
+    state.get()
+}
+
+#[tauri::command]
+pub fn set_fs_config(state: tauri::State<'_, FsConfigState>, config: FsConfig) {
+    *state.config.lock() = config;
+}
+
+/// Matches `name` against a pattern where `*` is any run of characters and
+/// `?` is exactly one.
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Last `*` seen and the name position it's currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Depth-first walk over the regular files below `root`, skipping ignored
+/// names. Symlinks are skipped unless `config.follow_symlinks` is set.
+/// `visit` returns false to stop early; the walk returns whether it ran to
+/// completion.
+pub(crate) fn walk_files(
+    root: &Path,
+    config: &FsConfig,
+    token: &CancelToken,
+    visit: &mut dyn FnMut(&Path, &fs::Metadata) -> bool,
+) -> Result<bool, String> {
+    let mut pending = vec![root.to_path_buf()];
+    // Canonical paths of directories already queued, when following links,
+    // so a cycle or two links to the same place are walked once
+    let mut seen = HashSet::new();
+    if config.follow_symlinks {
+        if let Ok(canonical) = root.canonicalize() {
+            seen.insert(canonical);
+        }
+    }
+
+    while let Some(dir) = pending.pop() {
+        // Unreadable subdirectories are skipped rather than failing the walk
+        let Ok(read_dir) = fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in read_dir.flatten() {
+            token.check()?;
+
+            if config.is_ignored(&entry.file_name().to_string_lossy()) {
+                continue;
+            }
+            let Ok(mut file_type) = entry.file_type() else {
+                continue;
+            };
+            let path = entry.path();
+
+            let mut target_metadata = None;
+            if file_type.is_symlink() {
+                if !config.follow_symlinks {
+                    continue;
+                }
+                // Dangling links are skipped
+                let Ok(metadata) = fs::metadata(&path) else {
+                    continue;
+                };
+                file_type = metadata.file_type();
+                target_metadata = Some(metadata);
+            }
+
+            if file_type.is_dir() {
+                if config.follow_symlinks {
+                    let Ok(canonical) = path.canonicalize() else {
+                        continue;
+                    };
+                    if !seen.insert(canonical) {
+                        continue;
+                    }
+                }
+                pending.push(path);
+            } else if file_type.is_file() {
+                let Some(metadata) = target_metadata.or_else(|| entry.metadata().ok()) else {
+                    continue;
+                };
+                if !visit(&path, &metadata) {
+                    return Ok(false);
+                }
+            }
+        }
+    }
+
+    Ok(true)
+}
+
+#[derive(Debug, Serialize)]
+pub struct DirectoryPage {
+    pub entries: Vec<FileEntry>,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortBy {
+    Name,
+    Size,
+    Modified,
+    /// By extension, then name
+    Type,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDir {
+    Asc,
+    Desc,
+}
+
+/// `ignore` and `show_hidden` override the managed `FsConfig` for this call.
+///
+/// Entries are sorted directories first, then by `sort_by` (name when not
+/// given) in `sort_dir` order. `name_filter` keeps only names containing it,
+/// or matching it as a glob when it has `*` or `?`; both are
+/// case-insensitive.
+#[tauri::command]
+pub fn read_directory(
+    config: tauri::State<'_, FsConfigState>,
+    path: &str,
+    ignore: Option<Vec<String>>,
+    show_hidden: Option<bool>,
+    sort_by: Option<SortBy>,
+    sort_dir: Option<SortDir>,
+    name_filter: Option<String>,
+) -> Result<Vec<FileEntry>, String> {
+    let mut entries = list_directory(path, &config.resolve(ignore, show_hidden))?;
+
+    if let Some(filter) = name_filter.filter(|filter| !filter.is_empty()) {
+        let filter = filter.to_lowercase();
+        let is_glob = filter.contains(['*', '?']);
+        entries.retain(|entry| {
+            let name = entry.name.to_lowercase();
+            if is_glob {
+                glob_match(&filter, &name)
+            } else {
+                name.contains(&filter)
+            }
+        });
+    }
+
+    // list_directory already returns the default order
+    let descending = sort_dir == Some(SortDir::Desc);
+    if sort_by.is_some() || descending {
+        sort_entries(&mut entries, sort_by.unwrap_or(SortBy::Name), descending);
+    }
+
+    Ok(entries)
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortKey {
+    Name,
+    Size(u64),
+    Modified(Option<SystemTime>),
+    Type(String),
+}
+
+fn sort_entries(entries: &mut Vec<FileEntry>, sort_by: SortBy, descending: bool) {
+    let mut keyed: Vec<(SortKey, String, FileEntry)> = entries
+        .drain(..)
+        .map(|entry| {
+            let metadata = || fs::metadata(&entry.path).ok();
+            let key = match sort_by {
+                SortBy::Name => SortKey::Name,
+                // Directory sizes say nothing useful, so those stay by name
+                SortBy::Size if entry.is_dir => SortKey::Size(0),
+                SortBy::Size => SortKey::Size(metadata().map_or(0, |m| m.len())),
+                SortBy::Modified => SortKey::Modified(metadata().and_then(|m| m.modified().ok())),
+                SortBy::Type => SortKey::Type(
+                    Path::new(&entry.name)
+                        .extension()
+                        .map(|ext| ext.to_string_lossy().to_lowercase())
+                        .unwrap_or_default(),
+                ),
+            };
+            (key, entry.name.to_lowercase(), entry)
+        })
+        .collect();
+
+    keyed.sort_by(|(a_key, a_name, a), (b_key, b_name, b)| {
+        let order = a_key.cmp(b_key).then_with(|| a_name.cmp(b_name));
+        let order = if descending { order.reverse() } else { order };
+        b.is_dir.cmp(&a.is_dir).then(order)
+    });
+
+    entries.extend(keyed.into_iter().map(|(_, _, entry)| entry));
 }
 
 #[tauri::command]
-pub fn read_directory(path: &str) -> Result<Vec<FileEntry>, String> {
+pub fn read_directory_paged(
+    config: tauri::State<'_, FsConfigState>,
+    path: &str,
+    offset: usize,
+    limit: usize,
+    ignore: Option<Vec<String>>,
+    show_hidden: Option<bool>,
+) -> Result<DirectoryPage, String> {
+    // Pages are cut from the fully sorted listing so they stay stable
+    // between calls as long as the directory doesn't change
+    let entries = list_directory(path, &config.resolve(ignore, show_hidden))?;
+    let total = entries.len();
+
+    Ok(DirectoryPage {
+        entries: entries.into_iter().skip(offset).take(limit).collect(),
+        total,
+    })
+}
+
+fn list_directory(path: &str, config: &FsConfig) -> Result<Vec<FileEntry>, String> {
     let dir_path = Path::new(path);
 
     if !dir_path.exists() {
@@ -31,20 +447,20 @@ pub fn read_directory(path: &str) -> Result<Vec<FileEntry>, String> {
                 let file_path = entry.path();
                 let is_hidden = file_name.starts_with('.');
 
-                // Skip certain directories/files
-                if file_name == "node_modules"
-                    || file_name == ".git"
-                    || file_name == "target"
-                    || file_name == ".DS_Store"
-                {
+                if config.is_ignored(&file_name) || (is_hidden && !config.show_hidden) {
                     continue;
                 }
 
+                // Follows symlinks, like the is_dir check always has
+                let kind = fs::metadata(&file_path)
+                    .map(|metadata| FileKind::of(metadata.file_type()))
+                    .unwrap_or(FileKind::Other);
                 entries.push(FileEntry {
                     name: file_name,
                     path: file_path.to_string_lossy().to_string(),
-                    is_dir: file_path.is_dir(),
+                    is_dir: kind == FileKind::Dir,
                     is_hidden,
+                    kind,
                 });
             }
         }
@@ -63,40 +479,3462 @@ pub fn read_directory(path: &str) -> Result<Vec<FileEntry>, String> {
     Ok(entries)
 }
 
+// Folders whose counts are kept; past this the least recently asked-for
+// one is dropped
+const ENTRY_COUNT_CACHE_SIZE: usize = 4096;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct EntryCount {
+    pub files: usize,
+    pub dirs: usize,
+}
+
+struct CachedCount {
+    // The folder's mtime when counted; adding, removing or renaming a child
+    // changes it
+    modified: SystemTime,
+    count: EntryCount,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct CountCache {
+    entries: HashMap<PathBuf, CachedCount>,
+    // Counts depend on the ignore list, so a config change empties the cache
+    config: Option<FsConfig>,
+    clock: u64,
+}
+
+/// Cache behind `count_directory_entries`, keyed by canonical folder path
+/// and mtime.
+/// Cloned into watchers so changes they see drop the affected folders even
+/// on filesystems with coarse mtimes.
+#[derive(Clone)]
+pub struct EntryCountState {
+    cache: Arc<Mutex<CountCache>>,
+}
+
+impl EntryCountState {
+    pub fn new() -> Self {
+        Self {
+            cache: Arc::new(Mutex::new(CountCache::default())),
+        }
+    }
+
+    // Forgets `path` and the folder containing it. Event paths come in the
+    // form that was watched, so they're resolved the way keys are; the
+    // parent is resolved separately since `path` may be gone already
+    pub(crate) fn invalidate(&self, path: &Path) {
+        let mut cache = self.cache.lock();
+        cache.entries.remove(path);
+        if let Ok(resolved) = fs::canonicalize(path) {
+            cache.entries.remove(&resolved);
+        }
+        if let Some(parent) = path.parent() {
+            cache.entries.remove(parent);
+            if let Ok(resolved) = fs::canonicalize(parent) {
+                cache.entries.remove(&resolved);
+            }
+        }
+    }
+}
+
+impl Default for EntryCountState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Number of immediate children of `path`, files and folders separately,
+/// skipping what `read_directory` would skip. Cached until the folder
+/// changes, so repeated tree renders don't recount.
+#[tauri::command]
+pub fn count_directory_entries(
+    config: tauri::State<'_, FsConfigState>,
+    counts: tauri::State<'_, EntryCountState>,
+    path: &str,
+) -> Result<EntryCount, String> {
+    let dir_path = Path::new(path);
+    // Read before listing, so a change made during the count leaves a stale
+    // mtime behind and the next call counts again
+    let modified = fs::metadata(dir_path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| format!("Failed to read directory metadata: {}", e))?;
+    let config = config.get();
+    // Watchers invalidate by canonical path, so that's the key here too
+    let key = fs::canonicalize(dir_path)
+        .map_err(|e| format!("Failed to resolve directory path: {}", e))?;
+
+    {
+        let mut cache = counts.cache.lock();
+        if cache.config.as_ref() != Some(&config) {
+            cache.entries.clear();
+            cache.config = Some(config.clone());
+        }
+        cache.clock += 1;
+        let clock = cache.clock;
+        if let Some(cached) = cache.entries.get_mut(&key) {
+            if cached.modified == modified {
+                cached.last_used = clock;
+                return Ok(cached.count);
+            }
+        }
+    }
+
+    let entries = list_directory(path, &config)?;
+    let dirs = entries.iter().filter(|entry| entry.is_dir).count();
+    let count = EntryCount {
+        files: entries.len() - dirs,
+        dirs,
+    };
+
+    let mut cache = counts.cache.lock();
+    if cache.entries.len() >= ENTRY_COUNT_CACHE_SIZE {
+        let oldest = cache
+            .entries
+            .iter()
+            .min_by_key(|(_, cached)| cached.last_used)
+            .map(|(path, _)| path.clone());
+        if let Some(oldest) = oldest {
+            cache.entries.remove(&oldest);
+        }
+    }
+    let last_used = cache.clock;
+    cache.entries.insert(
+        key,
+        CachedCount {
+            modified,
+            count,
+            last_used,
+        },
+    );
+    Ok(count)
+}
+
+/// Named pipes, sockets and devices are refused with a "Not a regular
+/// file" error rather than opened, since reading them can block or never
+/// end, and so are files over 10 MB. With `timeout_ms`, a read that takes
+/// longer (say on a stalled network mount) fails with a "Timeout:" error
+/// instead of hanging.
+#[tauri::command]
+pub fn read_file(path: &str, timeout_ms: Option<u64>) -> Result<String, String> {
+    let owned = path.to_string();
+    with_timeout(timeout_ms, path, move || {
+        ensure_regular_file(&owned)?;
+        let size = fs::metadata(&owned)
+            .map_err(|e| format!("Failed to read file metadata: {}", e))?
+            .len();
+        if size > MAX_TEXT_FILE_SIZE {
+            return Err(format!(
+                "File is too large to open ({} bytes, limit is {}): {}",
+                size, MAX_TEXT_FILE_SIZE, owned
+            ));
+        }
+        fs::read_to_string(&owned).map_err(|e| format!("Failed to read file: {}", e))
+    })
+}
+
+// Runs `read` on a worker thread and gives up on it after `timeout_ms`. A
+// read stuck in the kernel can't be interrupted, so the worker is left to
+// finish on its own; its result is then dropped and the thread exits
+fn with_timeout<T: Send + 'static>(
+    timeout_ms: Option<u64>,
+    path: &str,
+    read: impl FnOnce() -> Result<T, String> + Send + 'static,
+) -> Result<T, String> {
+    let Some(timeout_ms) = timeout_ms else {
+        return read();
+    };
+
+    let (sender, receiver) = std::sync::mpsc::sync_channel(1);
+    std::thread::spawn(move || {
+        let _ = sender.send(read());
+    });
+    match receiver.recv_timeout(std::time::Duration::from_millis(timeout_ms)) {
+        Ok(result) => result,
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => Err(format!(
+            "Timeout: reading {} took longer than {} ms",
+            path, timeout_ms
+        )),
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => {
+            Err(format!("Failed to read file: reader stopped: {}", path))
+        }
+    }
+}
+
+/// Byte-order mark found at the start of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Bom {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl Bom {
+    fn detect(bytes: &[u8]) -> Option<Self> {
+        [Bom::Utf8, Bom::Utf16Le, Bom::Utf16Be]
+            .into_iter()
+            .find(|bom| bytes.starts_with(bom.bytes()))
+    }
+
+    fn bytes(self) -> &'static [u8] {
+        match self {
+            Bom::Utf8 => &[0xEF, 0xBB, 0xBF],
+            Bom::Utf16Le => &[0xFF, 0xFE],
+            Bom::Utf16Be => &[0xFE, 0xFF],
+        }
+    }
+
+    fn decode(self, body: &[u8]) -> Option<String> {
+        let units = |to_u16: fn([u8; 2]) -> u16| {
+            if !body.len().is_multiple_of(2) {
+                return None;
+            }
+            let units = body.chunks_exact(2).map(|pair| to_u16([pair[0], pair[1]]));
+            char::decode_utf16(units)
+                .collect::<Result<String, _>>()
+                .ok()
+        };
+
+        match self {
+            Bom::Utf8 => String::from_utf8(body.to_vec()).ok(),
+            Bom::Utf16Le => units(u16::from_le_bytes),
+            Bom::Utf16Be => units(u16::from_be_bytes),
+        }
+    }
+
+    // The BOM followed by `content` in the encoding it announces
+    fn encode(self, content: &str) -> Vec<u8> {
+        let mut bytes = self.bytes().to_vec();
+        match self {
+            Bom::Utf8 => bytes.extend_from_slice(content.as_bytes()),
+            Bom::Utf16Le => bytes.extend(content.encode_utf16().flat_map(u16::to_le_bytes)),
+            Bom::Utf16Be => bytes.extend(content.encode_utf16().flat_map(u16::to_be_bytes)),
+        }
+        bytes
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileDetails {
+    /// File content with any BOM removed
+    pub content: String,
+    pub bom: Option<Bom>,
+}
+
+/// Like `read_file`, but strips and reports a leading BOM (decoding UTF-16
+/// when the BOM says so). Save with `write_file(.., preserve_bom: true)` to
+/// keep the file's BOM exactly as it was.
 #[tauri::command]
-pub fn read_file(path: &str) -> Result<String, String> {
+pub fn read_file_detailed(path: &str) -> Result<FileDetails, String> {
     let file_path = Path::new(path);
+    ensure_regular_file(path)?;
 
-    if !file_path.exists() {
-        return Err(format!("File does not exist: {}", path));
+    let size = fs::metadata(file_path)
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?
+        .len();
+    if size > MAX_TEXT_FILE_SIZE {
+        return Err(format!(
+            "File is too large to open ({} bytes, limit is {}): {}",
+            size, MAX_TEXT_FILE_SIZE, path
+        ));
+    }
+
+    let bytes = fs::read(file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let bom = Bom::detect(&bytes);
+    let body = &bytes[bom.map_or(0, |bom| bom.bytes().len())..];
+
+    let content = bom
+        .unwrap_or(Bom::Utf8)
+        .decode(body)
+        .ok_or_else(|| format!("Failed to read file: invalid text encoding: {}", path))?;
+
+    Ok(FileDetails { content, bom })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionFormat {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl CompressionFormat {
+    fn detect(magic: &[u8]) -> Option<Self> {
+        match magic {
+            [0x1F, 0x8B, ..] => Some(CompressionFormat::Gzip),
+            [0x28, 0xB5, 0x2F, 0xFD, ..] => Some(CompressionFormat::Zstd),
+            [b'B', b'Z', b'h', ..] => Some(CompressionFormat::Bzip2),
+            _ => None,
+        }
+    }
+}
+
+// ruzstd's `StreamingDecoder` stops after one frame, so this starts a new
+// one whenever a frame ends with input left, like `zstd -d` does
+struct MultiZstdDecoder<R: BufRead> {
+    decoder: Option<ruzstd::decoding::StreamingDecoder<R, ruzstd::decoding::FrameDecoder>>,
+}
+
+impl<R: BufRead> MultiZstdDecoder<R> {
+    fn new(source: R) -> Result<Self, String> {
+        let decoder = ruzstd::decoding::StreamingDecoder::new(source)
+            .map_err(|e| format!("Failed to decompress file: {}", e))?;
+        Ok(Self {
+            decoder: Some(decoder),
+        })
+    }
+}
+
+impl<R: BufRead> Read for MultiZstdDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            let Some(decoder) = self.decoder.as_mut() else {
+                return Ok(0);
+            };
+            let read = decoder.read(buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            let (mut source, frame) = self.decoder.take().unwrap().into_parts();
+            if source.fill_buf()?.is_empty() {
+                return Ok(0);
+            }
+            let next = ruzstd::decoding::StreamingDecoder::new_with_decoder(source, frame)
+                .map_err(std::io::Error::other)?;
+            self.decoder = Some(next);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DecompressedFile {
+    pub content: String,
+    /// How the file was compressed, `None` for a plain file
+    pub compression: Option<CompressionFormat>,
+}
+
+/// Reads a text file, decompressing it first when its magic bytes say it's
+/// gzip, zstd or bzip2, whatever the extension. The size limit applies to
+/// the decompressed text, so a small archive that expands enormously is
+/// refused instead of filling memory.
+#[tauri::command(async)]
+pub fn read_file_auto(path: String) -> Result<DecompressedFile, String> {
+    ensure_regular_file(&path)?;
+    let mut file = fs::File::open(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let mut magic = Vec::with_capacity(4);
+    (&mut file)
+        .take(4)
+        .read_to_end(&mut magic)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    file.seek(SeekFrom::Start(0))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let compression = CompressionFormat::detect(&magic);
+    let file = std::io::BufReader::new(file);
+    let reader: Box<dyn Read> = match compression {
+        // Multi-member readers, as `cat a.gz b.gz` is still a valid gzip file
+        Some(CompressionFormat::Gzip) => Box::new(flate2::bufread::MultiGzDecoder::new(file)),
+        Some(CompressionFormat::Bzip2) => Box::new(bzip2::bufread::MultiBzDecoder::new(file)),
+        Some(CompressionFormat::Zstd) => Box::new(MultiZstdDecoder::new(file)?),
+        None => Box::new(file),
+    };
+
+    // One byte past the limit tells a file that's exactly at it from one
+    // that's over
+    let mut bytes = Vec::new();
+    reader
+        .take(MAX_TEXT_FILE_SIZE + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| match compression {
+            Some(_) => format!("Failed to decompress file: {}", e),
+            None => format!("Failed to read file: {}", e),
+        })?;
+    if bytes.len() as u64 > MAX_TEXT_FILE_SIZE {
+        return Err(format!(
+            "File is too large to open (over {} bytes{}): {}",
+            MAX_TEXT_FILE_SIZE,
+            if compression.is_some() {
+                " decompressed"
+            } else {
+                ""
+            },
+            path
+        ));
+    }
+
+    let content = String::from_utf8(bytes)
+        .map_err(|_| format!("Failed to read file: invalid text encoding: {}", path))?;
+    Ok(DecompressedFile {
+        content,
+        compression,
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileWithLanguage {
+    pub content: String,
+    /// Editor language id, `plaintext` when it couldn't be determined
+    pub language: String,
+}
+
+/// Reads a file along with its language, so every view highlights it the
+/// same way.
+#[tauri::command]
+pub fn read_file_with_language(path: &str) -> Result<FileWithLanguage, String> {
+    let content = read_file(path, None)?;
+    let language = detect_language(Path::new(path), &content).unwrap_or("plaintext");
+
+    Ok(FileWithLanguage {
+        content,
+        language: language.to_string(),
+    })
+}
+
+fn detect_language(path: &Path, content: &str) -> Option<&'static str> {
+    match path.extension() {
+        Some(ext) => language_for_extension(&ext.to_string_lossy().to_lowercase()),
+        None => path
+            .file_name()
+            .and_then(|name| language_for_file_name(&name.to_string_lossy()))
+            .or_else(|| sniff_language(content)),
+    }
+}
+
+fn language_for_extension(ext: &str) -> Option<&'static str> {
+    Some(match ext {
+        "ts" | "tsx" | "mts" | "cts" => "typescript",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "json" => "json",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "less" => "less",
+        "md" | "markdown" => "markdown",
+        "py" | "pyw" => "python",
+        "rs" => "rust",
+        "go" => "go",
+        "java" => "java",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" => "cpp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "kt" | "kts" => "kotlin",
+        "sql" => "sql",
+        "yaml" | "yml" => "yaml",
+        "xml" | "svg" => "xml",
+        "sh" | "bash" | "zsh" => "shell",
+        "toml" => "toml",
+        "ini" => "ini",
+        "pl" | "pm" => "perl",
+        "lua" => "lua",
+        _ => return None,
+    })
+}
+
+fn language_for_file_name(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "Dockerfile" | "Containerfile" => "dockerfile",
+        "Makefile" | "makefile" | "GNUmakefile" => "makefile",
+        ".bashrc" | ".bash_profile" | ".zshrc" | ".zprofile" | ".profile" => "shell",
+        _ => return None,
+    })
+}
+
+// Looks at the shebang (`#!/bin/bash`, `#!/usr/bin/env python3`) or the
+// opening tag of extensionless files
+fn sniff_language(content: &str) -> Option<&'static str> {
+    let first_line = content.lines().next()?.trim();
+
+    if let Some(shebang) = first_line.strip_prefix("#!") {
+        let mut words = shebang.split_whitespace();
+        let mut interpreter = words.next()?.rsplit('/').next()?;
+        if interpreter == "env" {
+            interpreter = words.find(|word| !word.starts_with('-'))?;
+        }
+        // python3, python3.12, ruby2.7
+        let interpreter = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+        return Some(match interpreter {
+            "sh" | "bash" | "zsh" | "dash" | "ksh" | "ash" => "shell",
+            "python" => "python",
+            "node" => "javascript",
+            "deno" | "ts-node" | "bun" => "typescript",
+            "ruby" => "ruby",
+            "perl" => "perl",
+            "php" => "php",
+            "lua" => "lua",
+            _ => return None,
+        });
+    }
+
+    let lower = first_line.to_ascii_lowercase();
+    if lower.starts_with("<?php") {
+        Some("php")
+    } else if lower.starts_with("<?xml") {
+        Some("xml")
+    } else if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+        Some("html")
+    } else {
+        None
     }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StableRead {
+    pub content: String,
+    /// Byte offset just past the returned content; pass it back to resume
+    pub offset: u64,
+    /// The file shrank below the requested offset (truncated or rotated),
+    /// so reading restarted from the beginning
+    pub reset: bool,
+}
+
+/// Reads a file that another process may be appending to, returning only
+/// complete lines. A trailing line without a newline is held back until a
+/// later call finds it finished, so a last line that never gets a newline
+/// is never returned.
+#[tauri::command]
+pub fn read_file_stable(path: &str, offset: Option<u64>) -> Result<StableRead, String> {
+    let file_path = Path::new(path);
 
     if !file_path.is_file() {
         return Err(format!("Path is not a file: {}", path));
     }
 
-    fs::read_to_string(file_path).map_err(|e| format!("Failed to read file: {}", e))
+    let mut file = fs::File::open(file_path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let size = file
+        .metadata()
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?
+        .len();
+
+    let requested = offset.unwrap_or(0);
+    let reset = requested > size;
+    let start = if reset { 0 } else { requested };
+    let len = (size - start).min(MAX_TEXT_FILE_SIZE);
+
+    let mut bytes = Vec::with_capacity(len as usize);
+    file.seek(SeekFrom::Start(start))
+        .and_then(|_| (&mut file).take(len).read_to_end(&mut bytes))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    // Whatever follows the last newline may be a line the writer is still in
+    // the middle of, however still the file looks, so it waits for a later
+    // call. Only a single line longer than the read limit has to be split
+    let hit_limit = start + len < size;
+    let complete = match bytes.iter().rposition(|&b| b == b'\n') {
+        Some(last_newline) => last_newline + 1,
+        None if hit_limit => bytes.len(),
+        None => 0,
+    };
+    bytes.truncate(complete);
+
+    Ok(StableRead {
+        content: String::from_utf8_lossy(&bytes).to_string(),
+        offset: start + complete as u64,
+        reset,
+    })
 }
 
-#[tauri::command]
-pub fn write_file(path: &str, content: &str) -> Result<(), String> {
+#[derive(Debug, Serialize)]
+pub struct FileLines {
+    /// Lines without their `\n` / `\r\n` terminators
+    pub lines: Vec<String>,
+    /// More lines follow the ones returned
+    pub has_more: bool,
+    /// The last line was cut short at the 10 MB read limit
+    pub truncated: bool,
+}
+
+/// Returns up to `count` lines starting at the zero-based `start_line`.
+/// Lines before the start are skipped by scanning for newlines, so the
+/// cost depends on how far into the file the range is, not on its size.
+/// At most 10 MB is read per call; a line running past that is cut and
+/// flagged `truncated`. `timeout_ms` works as for `read_file`.
+#[tauri::command(async)]
+pub fn read_file_from_line(
+    path: String,
+    start_line: u64,
+    count: usize,
+    timeout_ms: Option<u64>,
+) -> Result<FileLines, String> {
+    let display = path.clone();
+    with_timeout(timeout_ms, &display, move || {
+        read_lines_from(&path, start_line, count)
+    })
+}
+
+fn read_lines_from(path: &str, start_line: u64, count: usize) -> Result<FileLines, String> {
     let file_path = Path::new(path);
 
-    // Create parent directories if they don't exist
-    if let Some(parent) = file_path.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create parent directories: {}", e))?;
+    if !file_path.is_file() {
+        return Err(format!("Path is not a file: {}", path));
+    }
+
+    let file = fs::File::open(file_path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut reader = std::io::BufReader::new(file);
+    let read_err = |e: std::io::Error| format!("Failed to read file: {}", e);
+
+    let mut skipped = 0;
+    while skipped < start_line {
+        let chunk = reader.fill_buf().map_err(read_err)?;
+        if chunk.is_empty() {
+            break;
+        }
+        // Consume up to and including the newline that ends the last line
+        // to skip, or the whole chunk if it doesn't get that far
+        let mut consumed = chunk.len();
+        for (i, _) in chunk.iter().enumerate().filter(|(_, &b)| b == b'\n') {
+            skipped += 1;
+            if skipped == start_line {
+                consumed = i + 1;
+                break;
+            }
+        }
+        reader.consume(consumed);
+    }
+
+    let mut lines = Vec::with_capacity(count.min(1024));
+    let mut total = 0;
+    let mut truncated = false;
+    let mut line = Vec::new();
+    while lines.len() < count && total < MAX_TEXT_FILE_SIZE {
+        line.clear();
+        // Bounded, so a single huge line (minified code) can't be read into
+        // memory whole
+        let mut limited = (&mut reader).take(MAX_TEXT_FILE_SIZE - total);
+        if limited.read_until(b'\n', &mut line).map_err(read_err)? == 0 {
+            break;
         }
+        total += line.len() as u64;
+        truncated = total >= MAX_TEXT_FILE_SIZE && !line.ends_with(b"\n");
+        let text = line
+            .strip_suffix(b"\n")
+            .map(|l| l.strip_suffix(b"\r").unwrap_or(l))
+            .unwrap_or(&line);
+        lines.push(String::from_utf8_lossy(text).to_string());
     }
 
-    fs::write(file_path, content).map_err(|e| format!("Failed to write file: {}", e))
+    let has_more = !reader.fill_buf().map_err(read_err)?.is_empty();
+
+    Ok(FileLines {
+        lines,
+        has_more,
+        truncated: truncated && has_more,
+    })
 }
 
-#[tauri::command]
-pub fn get_file_name(path: &str) -> String {
-    Path::new(path)
+/// Splits the file on `delimiter` (e.g. `"\0"` for `find -print0` output).
+/// A delimiter at the very end terminates the last record rather than
+/// starting an empty one. Invalid UTF-8 is replaced, not rejected, since
+/// records are often file names.
+#[tauri::command(async)]
+pub fn read_file_split(path: String, delimiter: String) -> Result<Vec<String>, String> {
+    if delimiter.is_empty() {
+        return Err("Delimiter must not be empty".to_string());
+    }
+
+    let file_path = Path::new(&path);
+    if !file_path.is_file() {
+        return Err(format!("Path is not a file: {}", path));
+    }
+
+    let size = fs::metadata(file_path)
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?
+        .len();
+    if size > MAX_TEXT_FILE_SIZE {
+        return Err(format!(
+            "File is too large to open ({} bytes, limit is {}): {}",
+            size, MAX_TEXT_FILE_SIZE, path
+        ));
+    }
+
+    let bytes = fs::read(file_path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(split_records(&bytes, delimiter.as_bytes()))
+}
+
+fn split_records(bytes: &[u8], delimiter: &[u8]) -> Vec<String> {
+    let mut records = Vec::new();
+    let mut rest = bytes;
+
+    while !rest.is_empty() {
+        let end = rest
+            .windows(delimiter.len())
+            .position(|window| window == delimiter);
+        let (record, next) = match end {
+            Some(end) => (&rest[..end], &rest[end + delimiter.len()..]),
+            None => (rest, &[][..]),
+        };
+        records.push(String::from_utf8_lossy(record).to_string());
+        rest = next;
+    }
+
+    records
+}
+
+const STREAM_CHUNK_SIZE: usize = 256 * 1024;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileChunk {
+    pub operation_id: String,
+    pub index: u64,
+    /// Base64-encoded bytes
+    pub data: String,
+    pub total: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FileComplete {
+    pub operation_id: String,
+    pub bytes: u64,
+}
+
+/// Streams a file to the frontend as `file-chunk` events (in order, 256 KiB
+/// each before base64) followed by `file-complete`, so large files never
+/// sit in memory whole. Cancellable with `cancel_operation(operation_id)`,
+/// in which case no `file-complete` is sent. Returns the bytes streamed.
+/// Named pipes, sockets and devices are refused, as they may never end.
+#[tauri::command(async)]
+pub fn stream_file(
+    app_handle: AppHandle,
+    operations: tauri::State<'_, OperationState>,
+    path: String,
+    operation_id: String,
+) -> Result<u64, String> {
+    let operation = operations.start(&operation_id)?;
+    let token = operation.token();
+
+    ensure_regular_file(&path)?;
+    let mut file = fs::File::open(&path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let total = file
+        .metadata()
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?
+        .len();
+
+    let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+    let mut index = 0;
+    let mut bytes = 0;
+    loop {
+        token.check()?;
+
+        let n =
+            read_full(&mut file, &mut buf).map_err(|e| format!("Failed to read file: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        let _ = app_handle.emit(
+            "file-chunk",
+            FileChunk {
+                operation_id: operation_id.clone(),
+                index,
+                data: BASE64.encode(&buf[..n]),
+                total,
+            },
+        );
+        index += 1;
+        bytes += n as u64;
+    }
+
+    let _ = app_handle.emit(
+        "file-complete",
+        FileComplete {
+            operation_id,
+            bytes,
+        },
+    );
+    Ok(bytes)
+}
+
+// Fills `buf` unless the file ends first, so every chunk but the last is full
+fn read_full(file: &mut fs::File, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match file.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+#[derive(Debug, Serialize)]
+pub struct LineCount {
+    /// Lines as an editor numbers them: a last line without a newline still
+    /// counts, and an empty file has none
+    pub lines: u64,
+    pub trailing_newline: bool,
+}
+
+/// Counts a file's lines by scanning it in chunks, so even huge files are
+/// never held in memory. Cancellable with `cancel_operation(operation_id)`.
+#[tauri::command(async)]
+pub fn count_file_lines(
+    operations: tauri::State<'_, OperationState>,
+    path: String,
+    operation_id: String,
+) -> Result<LineCount, String> {
+    let operation = operations.start(&operation_id)?;
+    let token = operation.token();
+    ensure_regular_file(&path)?;
+
+    let mut file = fs::File::open(&path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+    let mut newlines = 0;
+    let mut last_byte = None;
+    loop {
+        token.check()?;
+
+        let n =
+            read_full(&mut file, &mut buf).map_err(|e| format!("Failed to read file: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        newlines += buf[..n].iter().filter(|&&b| b == b'\n').count() as u64;
+        last_byte = Some(buf[n - 1]);
+    }
+
+    let trailing_newline = last_byte == Some(b'\n');
+    Ok(LineCount {
+        lines: match last_byte {
+            Some(_) if !trailing_newline => newlines + 1,
+            _ => newlines,
+        },
+        trailing_newline,
+    })
+}
+
+// Enough lines to see a file's habits without reading all of a huge one
+const INDENT_SAMPLE_BYTES: usize = 64 * 1024;
+const INDENT_SAMPLE_LINES: usize = 1000;
+// Deltas wider than this are alignment or continuation, not nesting
+const MAX_INDENT_WIDTH: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IndentStyle {
+    Tabs,
+    Spaces,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Indentation {
+    /// `None` when no sampled line is indented
+    pub style: Option<IndentStyle>,
+    /// Spaces per level; `None` for tabs, whose width is the editor's choice
+    pub width: Option<usize>,
+    /// 0 to 1: the share of indented lines using `style`, times the share of
+    /// indentation changes that are a multiple of `width` for spaces
+    pub confidence: f64,
+    pub lines_sampled: usize,
+}
+
+/// Guesses a file's indentation from its first 1000 lines (at most 64 KB).
+/// Mixed files report whichever style most lines use, with the confidence
+/// lowered accordingly.
+#[tauri::command]
+pub fn detect_indentation(path: String) -> Result<Indentation, String> {
+    ensure_regular_file(&path)?;
+    let mut file = fs::File::open(&path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut buf = vec![0u8; INDENT_SAMPLE_BYTES];
+    let n = read_full(&mut file, &mut buf).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(detect_indent(&String::from_utf8_lossy(&buf[..n])))
+}
+
+fn detect_indent(text: &str) -> Indentation {
+    let mut tab_lines = 0;
+    let mut space_lines = 0;
+    // Votes for each change in indentation between consecutive
+    // space-indented (or unindented) lines
+    let mut deltas = [0usize; MAX_INDENT_WIDTH + 1];
+    let mut previous = Some(0);
+    let mut smallest = None;
+    let mut lines_sampled = 0;
+
+    for line in text.lines().take(INDENT_SAMPLE_LINES) {
+        lines_sampled += 1;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with('\t') {
+            tab_lines += 1;
+            previous = None;
+            continue;
+        }
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        if spaces > 0 {
+            space_lines += 1;
+            smallest = Some(smallest.map_or(spaces, |s: usize| s.min(spaces)));
+        }
+        if let Some(previous) = previous {
+            let delta = spaces.abs_diff(previous);
+            if (1..=MAX_INDENT_WIDTH).contains(&delta) {
+                deltas[delta] += 1;
+            }
+        }
+        previous = Some(spaces);
+    }
+
+    let indented = tab_lines + space_lines;
+    if indented == 0 {
+        return Indentation {
+            style: None,
+            width: None,
+            confidence: 0.0,
+            lines_sampled,
+        };
+    }
+    if tab_lines > space_lines {
+        return Indentation {
+            style: Some(IndentStyle::Tabs),
+            width: None,
+            confidence: tab_lines as f64 / indented as f64,
+            lines_sampled,
+        };
+    }
+
+    // The most common change wins, ties going to the narrower. A change of
+    // one is usually a ` * ` comment continuation, so it only counts when
+    // nothing else was seen, and otherwise doesn't lower the confidence
+    let width = (2..=MAX_INDENT_WIDTH)
+        .filter(|&width| deltas[width] > 0)
+        .max_by_key(|&width| (deltas[width], std::cmp::Reverse(width)))
+        .or_else(|| (deltas[1] > 0).then_some(1))
+        .or(smallest)
+        .unwrap_or(1);
+    let counted = if width == 1 { 1 } else { 2 };
+    let total: usize = deltas[counted..].iter().sum();
+    let consistent = if total == 0 {
+        1.0
+    } else {
+        let agreeing: usize = (width..=MAX_INDENT_WIDTH)
+            .step_by(width)
+            .map(|delta| deltas[delta])
+            .sum();
+        agreeing as f64 / total as f64
+    };
+
+    Indentation {
+        style: Some(IndentStyle::Spaces),
+        width: Some(width),
+        confidence: space_lines as f64 / indented as f64 * consistent,
+        lines_sampled,
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContentMatch {
+    pub matches: bool,
+    /// Hex SHA-256 of the file on disk
+    pub hash: String,
+}
+
+/// Whether the file on disk holds exactly `content`, for the editor's dirty
+/// indicator. The file is hashed as it's read, so it's never loaded whole.
+#[tauri::command(async)]
+pub fn file_matches_content(path: String, content: String) -> Result<ContentMatch, String> {
+    let disk = hash_file(Path::new(&path))?;
+
+    Ok(ContentMatch {
+        matches: Sha256::digest(content.as_bytes()) == disk,
+        hash: disk.iter().map(|b| format!("{:02x}", b)).collect(),
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct HashedFile {
+    pub content: String,
+    /// Hex SHA-256 of the bytes read, as `file_matches_content` reports it
+    pub hash: String,
+    pub size: u64,
+    /// Milliseconds since the Unix epoch
+    pub modified: Option<u64>,
+}
+
+/// `read_file` plus what the editor needs to track the file afterwards, in
+/// one call. The hash covers exactly the bytes returned, so it can't
+/// describe a different version than `content` does.
+#[tauri::command]
+pub fn read_file_with_hash(path: &str) -> Result<HashedFile, String> {
+    ensure_regular_file(path)?;
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let metadata = file
+        .metadata()
+        .map_err(|e| format!("Failed to read file metadata: {}", e))?;
+    if metadata.len() > MAX_TEXT_FILE_SIZE {
+        return Err(format!(
+            "File is too large to open ({} bytes, limit is {}): {}",
+            metadata.len(),
+            MAX_TEXT_FILE_SIZE,
+            path
+        ));
+    }
+
+    let mut bytes = Vec::with_capacity(metadata.len() as usize);
+    file.read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let hash = Sha256::digest(&bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    let size = bytes.len() as u64;
+    let content = String::from_utf8(bytes)
+        .map_err(|_| format!("Failed to read file: invalid UTF-8: {}", path))?;
+
+    Ok(HashedFile {
+        content,
+        hash,
+        size,
+        modified: metadata.modified().ok().and_then(epoch_millis),
+    })
+}
+
+fn hash_file(path: &Path) -> Result<sha2::digest::Output<Sha256>, String> {
+    ensure_regular_file(&path.to_string_lossy())?;
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(hasher.finalize())
+}
+
+/// With `preserve_bom`, whatever BOM the existing file starts with is written
+/// back (re-encoding as UTF-16 if that's what it announces); a file without
+/// one never gains one.
+///
+/// With `verify`, the file is synced and read back, and a hash mismatch
+/// fails with `Verification failed: ...` so the editor can keep the buffer
+/// dirty.
+#[tauri::command]
+pub fn write_file(
+    locks: tauri::State<'_, FileLockState>,
+    path: &str,
+    content: &str,
+    fsync: Option<bool>,
+    preserve_bom: Option<bool>,
+    verify: Option<bool>,
+) -> Result<(), String> {
+    write_file_with(&locks, path, content, fsync, preserve_bom, verify)
+}
+
+fn write_file_with(
+    locks: &FileLockState,
+    path: &str,
+    content: &str,
+    fsync: Option<bool>,
+    preserve_bom: Option<bool>,
+    verify: Option<bool>,
+) -> Result<(), String> {
+    let file_path = Path::new(path);
+
+    locks.ensure_unlocked(file_path)?;
+    create_parent_dirs(file_path)?;
+
+    let bom = if preserve_bom.unwrap_or(false) {
+        existing_bom(file_path)
+    } else {
+        None
+    };
+    let bytes = match bom {
+        // Content may still carry the BOM as U+FEFF; don't write it twice
+        Some(bom) => bom.encode(content.strip_prefix('\u{feff}').unwrap_or(content)),
+        None => content.as_bytes().to_vec(),
+    };
+
+    let verify = verify.unwrap_or(false);
+    if !verify && !fsync.unwrap_or(false) {
+        return fs::write(file_path, bytes).map_err(|e| format!("Failed to write file: {}", e));
+    }
+
+    let mut file =
+        fs::File::create(file_path).map_err(|e| format!("Failed to write file: {}", e))?;
+    file.write_all(&bytes)
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+    file.sync_all()
+        .map_err(|e| format!("Failed to sync file: {}", e))?;
+    drop(file);
+
+    // The read back may be served from the page cache, so this catches
+    // what the filesystem got wrong rather than every media fault
+    if verify && hash_file(file_path)? != Sha256::digest(&bytes) {
+        return Err(format!(
+            "Verification failed: {} doesn't match what was written",
+            path
+        ));
+    }
+    Ok(())
+}
+
+fn existing_bom(path: &Path) -> Option<Bom> {
+    let mut head = [0u8; 3];
+    let mut file = fs::File::open(path).ok()?;
+    let len = file.read(&mut head).ok()?;
+    Bom::detect(&head[..len])
+}
+
+// Resizes in place with set_len, keeping the inode, so `tail -f` and other
+// followers holding the file open keep working
+#[tauri::command]
+pub fn truncate_file(
+    locks: tauri::State<'_, FileLockState>,
+    path: &str,
+    length: u64,
+) -> Result<(), String> {
+    let file_path = Path::new(path);
+    locks.ensure_unlocked(file_path)?;
+
+    if file_path.exists() && !file_path.is_file() {
+        return Err(format!("Path is not a file: {}", path));
+    }
+    if !file_path.exists() && length > 0 {
+        return Err(format!("File does not exist: {}", path));
+    }
+
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .create(length == 0)
+        .truncate(false)
+        .open(file_path)
+        .map_err(|e| format!("Failed to open file: {}", e))?;
+
+    file.set_len(length)
+        .map_err(|e| format!("Failed to truncate file: {}", e))
+}
+
+/// Files held open by `lock_file`, by canonical path. Dropping a handle
+/// releases its lock.
+pub struct FileLockState {
+    files: Mutex<BTreeMap<PathBuf, fs::File>>,
+}
+
+impl FileLockState {
+    pub fn new() -> Self {
+        Self {
+            files: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn lock(&self, path: &str) -> Result<(), String> {
+        ensure_regular_file(path)?;
+        let canonical =
+            fs::canonicalize(path).map_err(|e| format!("Failed to lock file: {}", e))?;
+
+        let mut locked = self.files.lock();
+        if locked.contains_key(&canonical) {
+            return Ok(());
+        }
+        let file = fs::File::open(&canonical).map_err(|e| format!("Failed to open file: {}", e))?;
+        // Called through the trait; std's own File::try_lock_shared differs
+        FileExt::try_lock_shared(&file).map_err(|e| {
+            if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() {
+                format!("Locked: {} is locked by another process", path)
+            } else {
+                format!("Failed to lock file: {}", e)
+            }
+        })?;
+        locked.insert(canonical, file);
+        Ok(())
+    }
+
+    fn unlock(&self, path: &str) -> Result<bool, String> {
+        let canonical =
+            fs::canonicalize(path).map_err(|e| format!("Failed to unlock file: {}", e))?;
+        Ok(self.files.lock().remove(&canonical).is_some())
+    }
+
+    // Fails with `Locked: ...` if `path` is held by lock_file
+    pub(crate) fn ensure_unlocked(&self, path: &Path) -> Result<(), String> {
+        let locked = self.files.lock();
+        if locked.is_empty() {
+            return Ok(());
+        }
+        // A path that doesn't resolve doesn't exist yet, so it can't be locked
+        match fs::canonicalize(path) {
+            Ok(canonical) if locked.contains_key(&canonical) => Err(format!(
+                "Locked: {} is open read-only, unlock it first",
+                path.display()
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for FileLockState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Opens `path` read-only and holds a shared lock on it until `unlock_file`,
+/// for view and compare modes. Meanwhile the app's own writes to it fail
+/// with `Locked: ...`. What other processes see depends on the platform: on
+/// Unix the lock is advisory and only holds off programs that take a lock
+/// themselves (some editors do), while on Windows it's mandatory and their
+/// writes to the file fail until it's unlocked. Locking an already locked
+/// path is a no-op.
+#[tauri::command]
+pub fn lock_file(locks: tauri::State<'_, FileLockState>, path: &str) -> Result<(), String> {
+    locks.lock(path)
+}
+
+/// Releases a lock taken with `lock_file`. Returns whether the path was
+/// locked.
+#[tauri::command]
+pub fn unlock_file(locks: tauri::State<'_, FileLockState>, path: &str) -> Result<bool, String> {
+    locks.unlock(path)
+}
+
+// Gives up after this many numbered names are taken
+const MAX_RENAME_ATTEMPTS: u32 = 10_000;
+
+/// Renames `from` to `to`, or if `to` is taken, to the first free
+/// `name (2).ext`, `name (3).ext`, ... next to it, like a file manager does
+/// on drop. Never replaces an existing entry. Returns the path used.
+#[tauri::command]
+pub fn rename_path_unique(from: &str, to: &str) -> Result<String, String> {
+    let source = Path::new(from);
+    if source.symlink_metadata().is_err() {
+        return Err(format!("Path does not exist: {}", from));
+    }
+
+    let target = Path::new(to);
+    let parent = target.parent().unwrap_or(Path::new(""));
+    let name = target
         .file_name()
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_else(|| path.to_string())
+        .ok_or_else(|| format!("Invalid destination: {}", to))?
+        .to_string_lossy()
+        .to_string();
+    // Directories keep dots in their names, e.g. `v1.2 (2)`
+    let split_extension = !source.is_dir();
+
+    for n in 1..=MAX_RENAME_ATTEMPTS {
+        let candidate = if n == 1 {
+            target.to_path_buf()
+        } else {
+            parent.join(numbered_name(&name, n, split_extension))
+        };
+        if rename_no_replace(source, &candidate).map_err(|e| format!("Failed to rename: {}", e))? {
+            return Ok(candidate.to_string_lossy().to_string());
+        }
+    }
+
+    Err(format!("No free name found for: {}", to))
+}
+
+fn numbered_name(name: &str, n: u32, split_extension: bool) -> String {
+    let path = Path::new(name);
+    match (split_extension, path.file_stem(), path.extension()) {
+        (true, Some(stem), Some(ext)) => {
+            format!(
+                "{} ({}).{}",
+                stem.to_string_lossy(),
+                n,
+                ext.to_string_lossy()
+            )
+        }
+        _ => format!("{} ({})", name, n),
+    }
+}
+
+// Returns Ok(false) instead of replacing `to` if it already exists. The
+// check is atomic where the OS supports it
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn rename_no_replace(from: &Path, to: &Path) -> std::io::Result<bool> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_from = CString::new(from.as_os_str().as_bytes())?;
+    let c_to = CString::new(to.as_os_str().as_bytes())?;
+    let rc = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            c_from.as_ptr(),
+            libc::AT_FDCWD,
+            c_to.as_ptr(),
+            libc::RENAME_NOREPLACE,
+        )
+    };
+    if rc == 0 {
+        return Ok(true);
+    }
+
+    let err = std::io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EEXIST) => Ok(false),
+        // Filesystem (or kernel) without RENAME_NOREPLACE support
+        Some(libc::EINVAL) | Some(libc::ENOSYS) => rename_if_absent(from, to),
+        _ => Err(err),
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn rename_no_replace(from: &Path, to: &Path) -> std::io::Result<bool> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_from = CString::new(from.as_os_str().as_bytes())?;
+    let c_to = CString::new(to.as_os_str().as_bytes())?;
+    if unsafe { libc::renamex_np(c_from.as_ptr(), c_to.as_ptr(), libc::RENAME_EXCL) } == 0 {
+        return Ok(true);
+    }
+
+    let err = std::io::Error::last_os_error();
+    match err.raw_os_error() {
+        Some(libc::EEXIST) => Ok(false),
+        Some(libc::ENOTSUP) => rename_if_absent(from, to),
+        _ => Err(err),
+    }
+}
+
+#[cfg(not(any(all(target_os = "linux", target_env = "gnu"), target_os = "macos")))]
+fn rename_no_replace(from: &Path, to: &Path) -> std::io::Result<bool> {
+    rename_if_absent(from, to)
+}
+
+// Racy fallback: another process could create `to` between the check and
+// the rename
+fn rename_if_absent(from: &Path, to: &Path) -> std::io::Result<bool> {
+    if to.symlink_metadata().is_ok() {
+        return Ok(false);
+    }
+    fs::rename(from, to).map(|_| true)
+}
+
+/// Writes to a temporary sibling and renames it over `path`, so readers see
+/// either the old or the new content, never a partial write.
+///
+/// With `fsync` the data is flushed to the device before the rename and the
+/// parent directory is flushed after it, so a power loss can't lose a save
+/// that was reported as successful. Each sync waits on the storage device,
+/// which typically adds milliseconds per save (much more on spinning or
+/// network disks), so it's off by default.
+#[tauri::command]
+pub fn write_file_atomic(
+    locks: tauri::State<'_, FileLockState>,
+    path: &str,
+    content: &str,
+    fsync: Option<bool>,
+) -> Result<(), String> {
+    let file_path = Path::new(path);
+
+    locks.ensure_unlocked(file_path)?;
+    create_parent_dirs(file_path)?;
+    write_atomic(file_path, content.as_bytes(), fsync.unwrap_or(false))
+}
+
+/// Creates `path` with `content`, failing with a `Conflict: ...` error if
+/// anything already exists there. The name is claimed with `create_new` so
+/// a concurrent writer can't slip in between the check and the write; the
+/// content then goes in atomically, so the file is never seen half-written.
+#[tauri::command]
+pub fn write_file_new(path: &str, content: &str) -> Result<(), String> {
+    let file_path = Path::new(path);
+    create_parent_dirs(file_path)?;
+
+    match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(file_path)
+    {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(format!("Conflict: file already exists: {}", path));
+        }
+        Err(e) => return Err(format!("Failed to create file: {}", e)),
+    }
+
+    write_atomic(file_path, content.as_bytes(), false).inspect_err(|_| {
+        // Don't leave the empty placeholder behind
+        let _ = fs::remove_file(file_path);
+    })
+}
+
+// Backups live next to the file in a hidden folder, named
+// `<file name>.<epoch ms>.bak` so they sort by age, or
+// `<file name>.<epoch ms>-<n>.bak` when saves land in the same millisecond
+const BACKUP_DIR: &str = ".history";
+const DEFAULT_MAX_BACKUPS: usize = 10;
+
+/// Copies the current file into `.history/` beside it, then writes the new
+/// content atomically. Only the newest `max_backups` (default 10) backups of
+/// the file are kept; 0 makes no backup and leaves older ones alone. Returns
+/// the backup path, or `None` if no backup was made.
+#[tauri::command]
+pub fn write_file_with_backup(
+    locks: tauri::State<'_, FileLockState>,
+    path: &str,
+    content: &str,
+    max_backups: Option<usize>,
+) -> Result<Option<String>, String> {
+    let file_path = Path::new(path);
+    // Before any backup work, so a locked file's history is left alone too
+    locks.ensure_unlocked(file_path)?;
+    create_parent_dirs(file_path)?;
+    let keep = max_backups.unwrap_or(DEFAULT_MAX_BACKUPS);
+    let file_name = file_path
+        .file_name()
+        .ok_or_else(|| format!("Invalid file path: {}", path))?
+        .to_string_lossy()
+        .to_string();
+    let backup_dir = file_path.with_file_name(BACKUP_DIR);
+
+    let backup = if keep > 0 && file_path.is_file() {
+        let stamp = epoch_millis(SystemTime::now()).unwrap_or(0);
+        let backup_path = create_backup(file_path, &backup_dir, &file_name, stamp)?;
+        prune_backups(&backup_dir, &file_name, keep);
+        Some(backup_path.to_string_lossy().to_string())
+    } else {
+        None
+    };
+
+    write_atomic(file_path, content.as_bytes(), false)?;
+    Ok(backup)
+}
+
+// Claims the name with `create_new`, so a second save in the same
+// millisecond gets the next suffix instead of overwriting the first backup
+fn create_backup(
+    file_path: &Path,
+    backup_dir: &Path,
+    file_name: &str,
+    stamp: u64,
+) -> Result<PathBuf, String> {
+    fs::create_dir_all(backup_dir)
+        .map_err(|e| format!("Failed to create backup directory: {}", e))?;
+    let mut source =
+        fs::File::open(file_path).map_err(|e| format!("Failed to back up file: {}", e))?;
+
+    for n in 0..MAX_RENAME_ATTEMPTS {
+        let name = match n {
+            0 => format!("{}.{}.bak", file_name, stamp),
+            n => format!("{}.{}-{}.bak", file_name, stamp, n),
+        };
+        let backup_path = backup_dir.join(name);
+        let mut backup = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&backup_path)
+        {
+            Ok(backup) => backup,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to back up file: {}", e)),
+        };
+        if let Err(e) = std::io::copy(&mut source, &mut backup) {
+            drop(backup);
+            let _ = fs::remove_file(&backup_path);
+            return Err(format!("Failed to back up file: {}", e));
+        }
+        return Ok(backup_path);
+    }
+    Err(format!(
+        "Failed to back up file: no free backup name for {}",
+        file_name
+    ))
+}
+
+fn prune_backups(backup_dir: &Path, file_name: &str, keep: usize) {
+    let Ok(read_dir) = fs::read_dir(backup_dir) else {
+        return;
+    };
+    let prefix = format!("{}.", file_name);
+
+    let mut backups: Vec<((u64, u32), PathBuf)> = read_dir
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let stamp = name.strip_prefix(&prefix)?.strip_suffix(".bak")?;
+            let order = match stamp.split_once('-') {
+                Some((millis, n)) => (millis.parse().ok()?, n.parse().ok()?),
+                None => (stamp.parse().ok()?, 0),
+            };
+            Some((order, entry.path()))
+        })
+        .collect();
+    if backups.len() <= keep {
+        return;
+    }
+
+    backups.sort();
+    for (_, path) in &backups[..backups.len() - keep] {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Renders `template` by replacing each `{{key}}` (spaces inside the braces
+/// are allowed) with its value from `variables`, then writes the result
+/// atomically. A placeholder with no matching variable is an error rather
+/// than being left in the file, so typos surface before anything is written.
+#[tauri::command]
+pub fn write_file_from_template(
+    locks: tauri::State<'_, FileLockState>,
+    path: &str,
+    template: &str,
+    variables: HashMap<String, String>,
+) -> Result<(), String> {
+    let content = render_template(template, &variables)?;
+    let file_path = Path::new(path);
+
+    locks.ensure_unlocked(file_path)?;
+    create_parent_dirs(file_path)?;
+    write_atomic(file_path, content.as_bytes(), false)
+}
+
+fn render_template(template: &str, variables: &HashMap<String, String>) -> Result<String, String> {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        // An unclosed `{{` is just text
+        let Some(end) = after.find("}}") else {
+            output.push_str(&rest[start..]);
+            return Ok(output);
+        };
+
+        let key = after[..end].trim();
+        let value = variables
+            .get(key)
+            .ok_or_else(|| format!("Missing template variable: {}", key))?;
+        output.push_str(value);
+        rest = &after[end + 2..];
+    }
+
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn create_parent_dirs(file_path: &Path) -> Result<(), String> {
+    // Create parent directories if they don't exist
+    if let Some(parent) = file_path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create parent directories: {}", e))?;
+        }
+    }
+    Ok(())
+}
+
+// Callers writing user files check FileLockState first
+pub(crate) fn write_atomic(file_path: &Path, bytes: &[u8], fsync: bool) -> Result<(), String> {
+    let temp_path = stage_temp_file(file_path, bytes, fsync)?;
+
+    if let Err(e) = fs::rename(&temp_path, file_path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Failed to replace file: {}", e));
+    }
+
+    if fsync {
+        sync_parent_dir(file_path)?;
+    }
+
+    Ok(())
+}
+
+// Writes `bytes` to a fresh temp sibling of `file_path` and returns its path,
+// removing the temp file again if anything fails
+fn stage_temp_file(file_path: &Path, bytes: &[u8], fsync: bool) -> Result<PathBuf, String> {
+    let temp_path = temp_sibling(file_path);
+
+    let result = (|| {
+        let mut file = fs::File::create(&temp_path)
+            .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+        file.write_all(bytes)
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+
+        // Keep the permissions of the file being replaced
+        if let Ok(metadata) = fs::metadata(file_path) {
+            let _ = file.set_permissions(metadata.permissions());
+        }
+
+        if fsync {
+            file.sync_all()
+                .map_err(|e| format!("Failed to sync file: {}", e))?;
+        }
+        Ok(())
+    })();
+
+    match result {
+        Ok(()) => Ok(temp_path),
+        Err(e) => {
+            let _ = fs::remove_file(&temp_path);
+            Err(e)
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BatchWrite {
+    pub path: String,
+    pub content: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchWriteResult {
+    pub path: String,
+    pub written: bool,
+    pub error: Option<String>,
+}
+
+/// Writes every file or none of them. All contents are staged in temp files
+/// first; only once every stage succeeds are they renamed into place, and a
+/// failed rename puts back the originals that were already replaced.
+/// Parent directories created for new files are left behind on rollback.
+#[tauri::command]
+pub fn write_files_batch(
+    locks: tauri::State<'_, FileLockState>,
+    files: Vec<BatchWrite>,
+) -> Vec<BatchWriteResult> {
+    let mut errors: Vec<Option<String>> = vec![None; files.len()];
+    let mut staged: Vec<PathBuf> = Vec::with_capacity(files.len());
+
+    for (i, file) in files.iter().enumerate() {
+        let target = Path::new(&file.path);
+        match locks
+            .ensure_unlocked(target)
+            .and_then(|_| create_parent_dirs(target))
+            .and_then(|_| stage_temp_file(target, file.content.as_bytes(), false))
+        {
+            Ok(temp) => staged.push(temp),
+            Err(e) => {
+                errors[i] = Some(e);
+                break;
+            }
+        }
+    }
+
+    if staged.len() == files.len() {
+        if let Err((i, e)) = commit_staged(&files, &staged) {
+            errors[i] = Some(e);
+        }
+    } else {
+        for temp in &staged {
+            let _ = fs::remove_file(temp);
+        }
+    }
+
+    let failed = errors.iter().any(Option::is_some);
+    files
+        .into_iter()
+        .zip(errors)
+        .map(|(file, error)| BatchWriteResult {
+            path: file.path,
+            written: !failed,
+            error: error.or_else(|| failed.then(|| "Batch rolled back".to_string())),
+        })
+        .collect()
+}
+
+// Renames every staged file over its target. Originals are moved aside
+// rather than overwritten so they can be restored if a later rename fails.
+fn commit_staged(files: &[BatchWrite], staged: &[PathBuf]) -> Result<(), (usize, String)> {
+    let mut committed: Vec<(&Path, Option<PathBuf>)> = Vec::with_capacity(staged.len());
+    let mut failure = None;
+
+    for (i, temp) in staged.iter().enumerate() {
+        let target = Path::new(&files[i].path);
+
+        let backup = if target.exists() {
+            let backup = temp_sibling(target);
+            if let Err(e) = fs::rename(target, &backup) {
+                failure = Some((i, format!("Failed to replace file: {}", e)));
+                break;
+            }
+            Some(backup)
+        } else {
+            None
+        };
+
+        if let Err(e) = fs::rename(temp, target) {
+            if let Some(backup) = &backup {
+                let _ = fs::rename(backup, target);
+            }
+            failure = Some((i, format!("Failed to replace file: {}", e)));
+            break;
+        }
+
+        committed.push((target, backup));
+    }
+
+    let Some(failure) = failure else {
+        for backup in committed.into_iter().filter_map(|(_, backup)| backup) {
+            let _ = fs::remove_file(backup);
+        }
+        return Ok(());
+    };
+
+    for (target, backup) in committed.into_iter().rev() {
+        match backup {
+            Some(backup) => {
+                let _ = fs::rename(backup, target);
+            }
+            None => {
+                let _ = fs::remove_file(target);
+            }
+        }
+    }
+    for temp in &staged[failure.0..] {
+        let _ = fs::remove_file(temp);
+    }
+
+    Err(failure)
+}
+
+// Hidden, uniquely named file next to the target so the rename stays on the
+// same filesystem
+pub(crate) fn temp_sibling(file_path: &Path) -> PathBuf {
+    let name = file_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    file_path.with_file_name(format!(".{}.{}.tmp", name, Uuid::new_v4().simple()))
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(file_path: &Path) -> Result<(), String> {
+    let parent = match file_path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    fs::File::open(parent)
+        .and_then(|dir| dir.sync_all())
+        .map_err(|e| format!("Failed to sync parent directory: {}", e))
+}
+
+// Windows has no way to flush a directory entry; the rename is already
+// durable once the file data has been synced
+#[cfg(not(unix))]
+fn sync_parent_dir(_file_path: &Path) -> Result<(), String> {
+    Ok(())
+}
+
+// Under the system temp directory, so leftovers from a crash are easy to
+// spot and clear
+const TEMP_DIR_NAME: &str = "claude-flow";
+
+/// Temp files handed out by `create_temp_file`, removed again by
+/// `cleanup_temp_files` or when the window closes.
+pub struct TempFileState {
+    files: Mutex<HashSet<PathBuf>>,
+}
+
+impl TempFileState {
+    pub fn new() -> Self {
+        Self {
+            files: Mutex::new(HashSet::new()),
+        }
+    }
+
+    // Returns how many files were removed; ones already gone don't count
+    pub fn cleanup(&self) -> usize {
+        let files = std::mem::take(&mut *self.files.lock());
+        files
+            .iter()
+            .filter(|path| fs::remove_file(path).is_ok())
+            .count()
+    }
+}
+
+impl Default for TempFileState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Creates an empty or `content`-filled file with a unique name in the
+/// app's temp directory and returns its path. `extension` (without the dot)
+/// lets editors and diff views pick the right language.
+#[tauri::command]
+pub fn create_temp_file(
+    temp_files: tauri::State<'_, TempFileState>,
+    content: Option<String>,
+    extension: Option<String>,
+) -> Result<String, String> {
+    let requested = extension.unwrap_or_default();
+    let extension = requested.trim_start_matches('.');
+    if extension.contains(['/', '\\']) {
+        return Err(format!("Invalid extension: {}", requested));
+    }
+
+    let dir = std::env::temp_dir().join(TEMP_DIR_NAME);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create temp directory: {}", e))?;
+    let mut name = Uuid::new_v4().simple().to_string();
+    if !extension.is_empty() {
+        name = format!("{}.{}", name, extension);
+    }
+    let path = dir.join(name);
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to create temp file: {}", e))?;
+    // Tracked before writing, so a failed write still gets cleaned up
+    temp_files.files.lock().insert(path.clone());
+    if let Some(content) = content {
+        file.write_all(content.as_bytes())
+            .map_err(|e| format!("Failed to write temp file: {}", e))?;
+    }
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Removes every file `create_temp_file` made this session and returns how
+/// many were still there.
+#[tauri::command]
+pub fn cleanup_temp_files(temp_files: tauri::State<'_, TempFileState>) -> usize {
+    temp_files.cleanup()
+}
+
+#[tauri::command]
+pub fn get_file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+#[derive(Debug, Serialize)]
+pub struct PathStat {
+    pub exists: bool,
+    pub size: Option<u64>,
+    /// Milliseconds since the Unix epoch
+    pub modified: Option<u64>,
+    /// Milliseconds since the Unix epoch, where the filesystem records it
+    pub created: Option<u64>,
+    /// Opaque identity that stays the same across renames and moves within
+    /// a volume, so a moved file can be told from a delete and a create.
+    /// Compare for equality only; it may be reused once the file is deleted.
+    pub file_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Metadata for each path, following symlinks. Missing paths and unreadable
+/// ones are marked in their own entry rather than failing the batch.
+#[tauri::command]
+pub fn stat_paths(paths: Vec<String>) -> HashMap<String, PathStat> {
+    paths
+        .into_iter()
+        .map(|path| {
+            let stat = match fs::metadata(&path) {
+                Ok(metadata) => PathStat {
+                    exists: true,
+                    size: Some(metadata.len()),
+                    modified: metadata.modified().ok().and_then(epoch_millis),
+                    created: metadata.created().ok().and_then(epoch_millis),
+                    file_id: file_id(Path::new(&path), &metadata),
+                    error: None,
+                },
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => PathStat {
+                    exists: false,
+                    size: None,
+                    modified: None,
+                    created: None,
+                    file_id: None,
+                    error: None,
+                },
+                // Unreadable paths are marked instead of failing the batch
+                Err(e) => PathStat {
+                    exists: Path::new(&path).symlink_metadata().is_ok(),
+                    size: None,
+                    modified: None,
+                    created: None,
+                    file_id: None,
+                    error: Some(format!("Failed to read metadata: {}", e)),
+                },
+            };
+            (path, stat)
+        })
+        .collect()
+}
+
+// Device and inode together identify a file on Unix
+#[cfg(unix)]
+fn file_id(_path: &Path, metadata: &fs::Metadata) -> Option<String> {
+    use std::os::unix::fs::MetadataExt;
+    Some(format!("{:x}-{:x}", metadata.dev(), metadata.ino()))
+}
+
+// Volume serial number and file index, which std only exposes on nightly.
+// Directories can only be opened with FILE_FLAG_BACKUP_SEMANTICS
+#[cfg(windows)]
+fn file_id(path: &Path, _metadata: &fs::Metadata) -> Option<String> {
+    use std::os::windows::fs::OpenOptionsExt;
+    use std::os::windows::io::AsRawHandle;
+    use windows_sys::Win32::Storage::FileSystem::{
+        GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION, FILE_FLAG_BACKUP_SEMANTICS,
+    };
+
+    let file = fs::OpenOptions::new()
+        .access_mode(0)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS)
+        .open(path)
+        .ok()?;
+    let mut info: BY_HANDLE_FILE_INFORMATION = unsafe { std::mem::zeroed() };
+    if unsafe { GetFileInformationByHandle(file.as_raw_handle(), &mut info) } == 0 {
+        return None;
+    }
+    let index = (u64::from(info.nFileIndexHigh) << 32) | u64::from(info.nFileIndexLow);
+    Some(format!("{:x}-{:x}", info.dwVolumeSerialNumber, index))
+}
+
+const DEFAULT_PREVIEW_BYTES: usize = 512;
+const MAX_PREVIEW_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Serialize)]
+pub struct FilePreview {
+    /// `None` for binary files and files that couldn't be read
+    pub head: Option<String>,
+    /// The file continues past `head`
+    pub truncated: bool,
+    pub is_binary: bool,
+    pub error: Option<String>,
+}
+
+/// The first `max_bytes` (default 512, at most 64 KB) of each file as text,
+/// cut back to a whole character, for tooltips. Binary files are flagged
+/// instead of previewed, and a failed read only marks its own entry.
+#[tauri::command(async)]
+pub fn preview_files(paths: Vec<String>, max_bytes: Option<usize>) -> HashMap<String, FilePreview> {
+    let limit = max_bytes
+        .unwrap_or(DEFAULT_PREVIEW_BYTES)
+        .clamp(1, MAX_PREVIEW_BYTES);
+
+    paths
+        .into_iter()
+        .map(|path| {
+            let preview = match preview_file(Path::new(&path), limit) {
+                Ok(preview) => preview,
+                Err(e) => FilePreview {
+                    head: None,
+                    truncated: false,
+                    is_binary: false,
+                    error: Some(e),
+                },
+            };
+            (path, preview)
+        })
+        .collect()
+}
+
+fn preview_file(path: &Path, limit: usize) -> Result<FilePreview, String> {
+    if !path.is_file() {
+        return Err(format!("Path is not a file: {}", path.display()));
+    }
+
+    // Sniffing needs more than a short preview, and one byte past the limit
+    // tells whether there's more
+    let mut bytes = Vec::new();
+    fs::File::open(path)
+        .and_then(|f| {
+            f.take(limit.max(BINARY_SNIFF_LEN) as u64 + 1)
+                .read_to_end(&mut bytes)
+        })
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    if looks_binary(&bytes) {
+        return Ok(FilePreview {
+            head: None,
+            truncated: false,
+            is_binary: true,
+            error: None,
+        });
+    }
+
+    let body = bytes.strip_prefix(Bom::Utf8.bytes()).unwrap_or(&bytes);
+    let (head, truncated) = utf8_head(body, limit);
+    Ok(FilePreview {
+        head: Some(head),
+        truncated,
+        is_binary: false,
+        error: None,
+    })
+}
+
+// At most `limit` bytes decoded lossily, dropping a character the cut would
+// split rather than showing it as U+FFFD
+fn utf8_head(bytes: &[u8], limit: usize) -> (String, bool) {
+    if bytes.len() <= limit {
+        return (String::from_utf8_lossy(bytes).to_string(), false);
+    }
+
+    let is_continuation = |byte: u8| byte & 0xC0 == 0x80;
+    // The byte just past the cut starts the next character; a UTF-8
+    // character has at most 3 continuation bytes to step back over
+    let mut end = limit;
+    while end > 0 && limit - end < 3 && is_continuation(bytes[end]) {
+        end -= 1;
+    }
+    if is_continuation(bytes[end]) {
+        // Not UTF-8 here anyway, so there's no character to keep whole
+        end = limit;
+    }
+    (String::from_utf8_lossy(&bytes[..end]).to_string(), true)
+}
+
+pub(crate) fn epoch_millis(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_millis() as u64)
+}
+
+// Bounds on a recent_files walk so a huge tree can't stall the query
+const RECENT_FILES_MAX_VISITED: usize = 200_000;
+const RECENT_FILES_TIME_BUDGET: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Debug, Serialize)]
+pub struct RecentFile {
+    pub path: String,
+    /// Milliseconds since the Unix epoch
+    pub modified: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecentFiles {
+    pub files: Vec<RecentFile>,
+    /// The walk hit its entry or time budget, so older parts of the tree
+    /// may be missing
+    pub truncated: bool,
+}
+
+/// Files under `root` modified within the last `days` days and/or the
+/// `limit` most recently modified ones, newest first. `follow_symlinks`
+/// overrides the `FsConfig` setting for this call. Cancellable with
+/// `cancel_operation(operation_id)`.
+#[tauri::command(async)]
+pub fn recent_files(
+    operations: tauri::State<'_, OperationState>,
+    config: tauri::State<'_, FsConfigState>,
+    root: String,
+    days: Option<u64>,
+    limit: Option<usize>,
+    follow_symlinks: Option<bool>,
+    operation_id: String,
+) -> Result<RecentFiles, String> {
+    let operation = operations.start(&operation_id)?;
+    let root_path = Path::new(&root);
+    if !root_path.is_dir() {
+        return Err(format!("Path is not a directory: {}", root));
+    }
+
+    // A window reaching back past the epoch (or past what the clock can
+    // represent) just means every file
+    let cutoff = days
+        .and_then(|d| d.checked_mul(24 * 60 * 60))
+        .and_then(|secs| SystemTime::now().checked_sub(std::time::Duration::from_secs(secs)))
+        .and_then(epoch_millis)
+        .unwrap_or(0);
+    let started = std::time::Instant::now();
+    let mut visited = 0;
+    let mut files = Vec::new();
+
+    let completed = walk_files(
+        root_path,
+        &config.for_walk(follow_symlinks),
+        &operation.token(),
+        &mut |path, metadata| {
+            visited += 1;
+            if let Some(modified) = metadata.modified().ok().and_then(epoch_millis) {
+                if modified >= cutoff {
+                    files.push(RecentFile {
+                        path: path.to_string_lossy().to_string(),
+                        modified,
+                    });
+                }
+            }
+            visited < RECENT_FILES_MAX_VISITED && started.elapsed() < RECENT_FILES_TIME_BUDGET
+        },
+    )?;
+
+    files.sort_by_key(|file| std::cmp::Reverse(file.modified));
+    if let Some(limit) = limit {
+        files.truncate(limit);
+    }
+
+    Ok(RecentFiles {
+        files,
+        truncated: !completed,
+    })
+}
+
+const CHANGED_FILES_PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ChangedFilesProgress {
+    pub operation_id: String,
+    /// Files looked at so far
+    pub visited: u64,
+    /// Of those, how many changed
+    pub changed: u64,
+}
+
+/// Files under `root` modified after `since` (milliseconds since the Unix
+/// epoch), newest first, for re-indexing only what changed since the last
+/// scan. Ignored entries are skipped and `follow_symlinks` overrides the
+/// `FsConfig` setting. Unlike `recent_files` the whole tree is walked; emits
+/// `changed-files-progress` along the way and stops on
+/// `cancel_operation(operation_id)`.
+#[tauri::command(async)]
+pub fn files_changed_since(
+    app_handle: AppHandle,
+    operations: tauri::State<'_, OperationState>,
+    config: tauri::State<'_, FsConfigState>,
+    root: String,
+    since: u64,
+    follow_symlinks: Option<bool>,
+    operation_id: String,
+) -> Result<Vec<RecentFile>, String> {
+    let operation = operations.start(&operation_id)?;
+    let root_path = Path::new(&root);
+    if !root_path.is_dir() {
+        return Err(format!("Path is not a directory: {}", root));
+    }
+
+    let mut progress = ChangedFilesProgress {
+        operation_id,
+        visited: 0,
+        changed: 0,
+    };
+    let mut last_emit = std::time::Instant::now();
+    let mut files = Vec::new();
+
+    walk_files(
+        root_path,
+        &config.for_walk(follow_symlinks),
+        &operation.token(),
+        &mut |path, metadata| {
+            progress.visited += 1;
+            if let Some(modified) = metadata.modified().ok().and_then(epoch_millis) {
+                if modified > since {
+                    progress.changed += 1;
+                    files.push(RecentFile {
+                        path: path.to_string_lossy().to_string(),
+                        modified,
+                    });
+                }
+            }
+            if last_emit.elapsed() >= CHANGED_FILES_PROGRESS_INTERVAL {
+                last_emit = std::time::Instant::now();
+                let _ = app_handle.emit("changed-files-progress", progress.clone());
+            }
+            true
+        },
+    )?;
+    let _ = app_handle.emit("changed-files-progress", progress);
+
+    files.sort_by_key(|file| std::cmp::Reverse(file.modified));
+    Ok(files)
+}
+
+/// Creates `link_path` pointing at `target`. A relative target is resolved
+/// from the link's directory, not the working directory.
+#[tauri::command]
+pub fn create_symlink(target: &str, link_path: &str) -> Result<(), String> {
+    let link = Path::new(link_path);
+    if link.symlink_metadata().is_ok() {
+        return Err(format!("Path already exists: {}", link_path));
+    }
+
+    symlink(target, link).map_err(|e| {
+        if cfg!(windows) && e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD) {
+            "Creating symbolic links on Windows requires administrator rights or Developer Mode"
+                .to_string()
+        } else {
+            format!("Failed to create symlink: {}", e)
+        }
+    })
+}
+
+/// The path a symlink points at, exactly as stored (possibly relative).
+#[tauri::command]
+pub fn read_symlink_target(path: &str) -> Result<String, String> {
+    fs::read_link(path)
+        .map(|target| target.to_string_lossy().to_string())
+        .map_err(|e| format!("Failed to read symlink: {}", e))
+}
+
+// Same limit as Linux's ELOOP, so a long but finite chain still resolves
+const MAX_SYMLINK_HOPS: usize = 40;
+
+#[derive(Debug, Serialize)]
+pub struct ResolvedSymlink {
+    pub path: String,
+    /// The chain ends at a path that doesn't exist; `path` is that target
+    pub broken: bool,
+}
+
+/// Follows a symlink chain to its final target. A chain that revisits a link
+/// fails with a "Symlink loop" error instead of spinning forever.
+#[tauri::command]
+pub fn resolve_symlink(path: &str) -> Result<ResolvedSymlink, String> {
+    let mut current = PathBuf::from(path);
+    let mut visited = HashSet::new();
+
+    loop {
+        let metadata = match fs::symlink_metadata(&current) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(ResolvedSymlink {
+                    path: current.to_string_lossy().to_string(),
+                    broken: true,
+                });
+            }
+            Err(e) => return Err(format!("Failed to read symlink: {}", e)),
+        };
+
+        if !metadata.file_type().is_symlink() {
+            // Directories along the way may be links too
+            let resolved = fs::canonicalize(&current).unwrap_or(current);
+            return Ok(ResolvedSymlink {
+                path: resolved.to_string_lossy().to_string(),
+                broken: false,
+            });
+        }
+
+        if !visited.insert(current.clone()) || visited.len() > MAX_SYMLINK_HOPS {
+            return Err(format!("Symlink loop: {}", path));
+        }
+
+        let target =
+            fs::read_link(&current).map_err(|e| format!("Failed to read symlink: {}", e))?;
+        // Relative targets are relative to the link's directory
+        let next = current.parent().unwrap_or(Path::new("")).join(target);
+        current = normalize_lexically(&next);
+    }
+}
+
+// Drops `.` and folds `..` without touching the filesystem, so the same link
+// reached by different spellings is recognised as already visited
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// `target` relative to `base`, e.g. `../lib/util.rs`, worked out from the
+/// paths alone (symlinks aren't resolved). Both must be absolute. A target
+/// on another drive or share is returned unchanged, and one equal to the
+/// base comes back as `.`.
+#[tauri::command]
+pub fn relative_path(base: &str, target: &str) -> Result<String, String> {
+    for path in [base, target] {
+        if !Path::new(path).is_absolute() {
+            return Err(format!("Path is not absolute: {}", path));
+        }
+    }
+
+    Ok(relative_to(
+        &normalize_lexically(Path::new(base)),
+        &normalize_lexically(Path::new(target)),
+    )
+    .map(|relative| relative.to_string_lossy().to_string())
+    .unwrap_or_else(|| target.to_string()))
+}
+
+// None when the paths start from different roots
+fn relative_to(base: &Path, target: &Path) -> Option<PathBuf> {
+    let base: Vec<_> = base.components().collect();
+    let target: Vec<_> = target.components().collect();
+    // Windows paths compare case-insensitively, `C:` and `c:` included
+    let same = |a: &std::path::Component, b: &std::path::Component| {
+        if cfg!(windows) {
+            a.as_os_str().eq_ignore_ascii_case(b.as_os_str())
+        } else {
+            a == b
+        }
+    };
+
+    match (base.first(), target.first()) {
+        (Some(a), Some(b)) if same(a, b) => {}
+        _ => return None,
+    }
+
+    let common = base
+        .iter()
+        .zip(&target)
+        .take_while(|(a, b)| same(a, b))
+        .count();
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &target[common..] {
+        relative.push(component);
+    }
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    Some(relative)
+}
+
+/// Canonicalizes `paths` and drops duplicates and anything inside another
+/// selected directory, so a batch operation touches each entry once. The
+/// final component isn't resolved, so a selected symlink stays the link
+/// rather than becoming its target. Paths that don't exist are normalized
+/// lexically. Kept paths are returned in the order they were given.
+#[tauri::command]
+pub fn normalize_path_set(paths: Vec<String>) -> Vec<String> {
+    let canonical = paths
+        .iter()
+        .map(|path| canonical_entry(Path::new(path)))
+        .collect();
+
+    minimal_cover(canonical)
+        .into_iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect()
+}
+
+fn canonical_entry(path: &Path) -> PathBuf {
+    let normalized = normalize_lexically(path);
+    let resolved = match (normalized.parent(), normalized.file_name()) {
+        (Some(parent), Some(name)) => {
+            let parent = if parent.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                parent
+            };
+            fs::canonicalize(parent).map(|parent| parent.join(name))
+        }
+        // A root or bare `..`
+        _ => fs::canonicalize(&normalized),
+    };
+    resolved.unwrap_or(normalized)
+}
+
+// Keeps each path that neither repeats nor lies below another path in the set
+fn minimal_cover(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut shallowest_first: Vec<&PathBuf> = paths.iter().collect();
+    shallowest_first.sort_by_key(|path| path.components().count());
+
+    let mut kept = HashSet::new();
+    for path in shallowest_first {
+        // ancestors() starts with the path itself, which catches duplicates
+        if !path.ancestors().any(|ancestor| kept.contains(ancestor)) {
+            kept.insert(path.clone());
+        }
+    }
+
+    // Removing as we go drops later duplicates
+    paths.into_iter().filter(|path| kept.remove(path)).collect()
+}
+
+// Raised by CreateSymbolicLinkW when the process lacks SeCreateSymbolicLinkPrivilege
+const ERROR_PRIVILEGE_NOT_HELD: i32 = 1314;
+
+#[cfg(unix)]
+pub(crate) fn symlink(link_target: &str, link: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(link_target, link)
+}
+
+// Windows needs to know whether the link points at a directory, so check
+// what the target resolves to right now
+#[cfg(windows)]
+pub(crate) fn symlink(link_target: &str, link: &Path) -> std::io::Result<()> {
+    let resolved = link.parent().unwrap_or(Path::new("")).join(link_target);
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(link_target, link)
+    } else {
+        std::os::windows::fs::symlink_file(link_target, link)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImageInfo {
+    /// Lowercase format name, e.g. "png", "jpeg", "webp"
+    pub format: String,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Reads an image's format and dimensions from its header, without decoding
+/// the pixel data.
+#[tauri::command]
+pub fn image_info(path: &str) -> Result<ImageInfo, String> {
+    ensure_regular_file(path)?;
+    let file = fs::File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let mut reader = std::io::BufReader::new(file);
+
+    let image_error = |e: imagesize::ImageError| match e {
+        imagesize::ImageError::NotSupported => format!("Not a recognized image: {}", path),
+        imagesize::ImageError::CorruptedImage => format!("Image header is corrupted: {}", path),
+        imagesize::ImageError::IoError(e) => format!("Failed to read file: {}", e),
+    };
+    let image_type = imagesize::reader_type(&mut reader).map_err(image_error)?;
+    let size = image_type.reader_size(&mut reader).map_err(image_error)?;
+
+    // Variants like `Heif(Hevc)` carry the codec; only the container matters here
+    let format = format!("{:?}", image_type);
+    let format = format.split('(').next().unwrap_or_default().to_lowercase();
+
+    Ok(ImageInfo {
+        format,
+        width: size.width,
+        height: size.height,
+    })
+}
+
+#[tauri::command]
+pub fn is_path_writable(path: &str) -> bool {
+    let target = Path::new(path);
+
+    if target.is_file() {
+        // Opening for append checks effective permission without truncating
+        return fs::OpenOptions::new().append(true).open(target).is_ok();
+    }
+
+    if target.is_dir() {
+        return is_dir_writable(target);
+    }
+
+    // The file doesn't exist yet. write_file creates missing parents, so the
+    // nearest existing ancestor decides whether the save can succeed.
+    let mut ancestor = target.parent();
+    while let Some(dir) = ancestor {
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+        if dir.is_dir() {
+            return is_dir_writable(dir);
+        }
+        ancestor = dir.parent();
+    }
+
+    false
+}
+
+#[cfg(unix)]
+fn is_dir_writable(dir: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(c_path) = CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // Creating an entry needs both write and search permission on the directory
+    unsafe { libc::access(c_path.as_ptr(), libc::W_OK | libc::X_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_dir_writable(dir: &Path) -> bool {
+    fs::metadata(dir)
+        .map(|m| !m.permissions().readonly())
+        .unwrap_or(false)
+}
+
+#[derive(Debug, Serialize)]
+pub struct FilePermissions {
+    /// Permission bits including setuid / setgid / sticky (Unix only)
+    pub mode: Option<u32>,
+    /// `ls`-style, e.g. `rwxr-xr-x`; just `r--` or `rw-` on Windows
+    pub display: String,
+    pub readonly: bool,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// Unix only, `None` when the ID has no name on this system
+    pub owner: Option<String>,
+    pub group: Option<String>,
+}
+
+#[tauri::command]
+pub fn get_permissions(path: &str) -> Result<FilePermissions, String> {
+    let metadata =
+        fs::metadata(path).map_err(|e| format!("Failed to read file metadata: {}", e))?;
+    Ok(file_permissions(&metadata))
+}
+
+#[cfg(unix)]
+fn file_permissions(metadata: &fs::Metadata) -> FilePermissions {
+    use std::os::unix::fs::MetadataExt;
+
+    let mode = metadata.mode() & 0o7777;
+    FilePermissions {
+        mode: Some(mode),
+        display: format_mode(mode),
+        readonly: metadata.permissions().readonly(),
+        uid: Some(metadata.uid()),
+        gid: Some(metadata.gid()),
+        owner: user_name(metadata.uid()),
+        group: group_name(metadata.gid()),
+    }
+}
+
+#[cfg(not(unix))]
+fn file_permissions(metadata: &fs::Metadata) -> FilePermissions {
+    let readonly = metadata.permissions().readonly();
+    FilePermissions {
+        mode: None,
+        display: if readonly { "r--" } else { "rw-" }.to_string(),
+        readonly,
+        uid: None,
+        gid: None,
+        owner: None,
+        group: None,
+    }
+}
+
+// Same letters as `ls -l`: s/S for setuid and setgid, t/T for sticky
+fn format_mode(mode: u32) -> String {
+    let special = [(0o4000, 's', 'S'), (0o2000, 's', 'S'), (0o1000, 't', 'T')];
+    let mut out = String::with_capacity(9);
+
+    for (class, (special_bit, with_exec, without_exec)) in special.into_iter().enumerate() {
+        let bits = (mode >> (6 - class * 3)) & 0o7;
+        out.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        out.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        out.push(match (mode & special_bit != 0, bits & 0o1 != 0) {
+            (true, true) => with_exec,
+            (true, false) => without_exec,
+            (false, true) => 'x',
+            (false, false) => '-',
+        });
+    }
+
+    out
+}
+
+#[cfg(unix)]
+fn user_name(uid: u32) -> Option<String> {
+    use std::ffi::CStr;
+
+    let mut buf = vec![0 as libc::c_char; 16 * 1024];
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    let rc = unsafe { libc::getpwuid_r(uid, &mut pwd, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+
+    let name = unsafe { CStr::from_ptr(pwd.pw_name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(unix)]
+fn group_name(gid: u32) -> Option<String> {
+    use std::ffi::CStr;
+
+    let mut buf = vec![0 as libc::c_char; 16 * 1024];
+    let mut grp: libc::group = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::group = std::ptr::null_mut();
+
+    let rc = unsafe { libc::getgrgid_r(gid, &mut grp, buf.as_mut_ptr(), buf.len(), &mut result) };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+
+    let name = unsafe { CStr::from_ptr(grp.gr_name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+/// `mode` is an octal string such as `"755"` or `"4755"`. On Windows only
+/// the owner write bit matters: without it the file is made readonly.
+#[tauri::command]
+pub fn set_permissions(path: &str, mode: &str) -> Result<(), String> {
+    let mode = u32::from_str_radix(mode.trim_start_matches("0o"), 8)
+        .ok()
+        .filter(|mode| *mode <= 0o7777)
+        .ok_or_else(|| format!("Invalid octal mode: {}", mode))?;
+
+    #[cfg(unix)]
+    let permissions = {
+        use std::os::unix::fs::PermissionsExt;
+        fs::Permissions::from_mode(mode)
+    };
+    #[cfg(not(unix))]
+    let permissions = {
+        let mut permissions = fs::metadata(path)
+            .map_err(|e| format!("Failed to read file metadata: {}", e))?
+            .permissions();
+        permissions.set_readonly(mode & 0o200 == 0);
+        permissions
+    };
+
+    fs::set_permissions(path, permissions).map_err(|e| format!("Failed to set permissions: {}", e))
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiskSpace {
+    pub total: u64,
+    /// Unused bytes, including any reserved for root
+    pub free: u64,
+    /// Bytes the current user can actually write
+    pub available: u64,
+}
+
+/// Space on the filesystem containing `path`. On Windows `free` and
+/// `available` are the same figure.
+#[tauri::command]
+pub fn disk_space(path: &str) -> Result<DiskSpace, String> {
+    let target = Path::new(path);
+    if !target.exists() {
+        return Err(format!("Path does not exist: {}", path));
+    }
+    volume_space(target)
+}
+
+#[cfg(unix)]
+fn volume_space(path: &Path) -> Result<DiskSpace, String> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| format!("Invalid path: {}", path.display()))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(format!(
+            "Failed to read disk space: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    // Block counts are in units of the fragment size
+    let unit = stat.f_frsize as u64;
+    Ok(DiskSpace {
+        total: stat.f_blocks as u64 * unit,
+        free: stat.f_bfree as u64 * unit,
+        available: stat.f_bavail as u64 * unit,
+    })
+}
+
+#[cfg(windows)]
+fn volume_space(path: &Path) -> Result<DiskSpace, String> {
+    // Not canonicalize: its `\\?\` prefix wouldn't match the mount points
+    let path = std::path::absolute(path).map_err(|e| format!("Failed to resolve path: {}", e))?;
+    let disks = sysinfo::Disks::new_with_refreshed_list();
+
+    // The deepest mount point containing the path, for mounted folders
+    let disk = disks
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().components().count())
+        .ok_or_else(|| format!("No volume found for: {}", path.display()))?;
+
+    Ok(DiskSpace {
+        total: disk.total_space(),
+        free: disk.available_space(),
+        available: disk.available_space(),
+    })
+}
+
+/// Folders `open_path_external` may hand to the OS, set by the frontend
+/// with `set_workspace_roots` as projects open and close. Empty until then,
+/// so nothing can be opened.
+pub struct WorkspaceRootsState {
+    roots: Mutex<Vec<PathBuf>>,
+}
+
+impl WorkspaceRootsState {
+    pub fn new() -> Self {
+        Self {
+            roots: Mutex::new(Vec::new()),
+        }
+    }
+
+    // Whether canonical `path` is one of the roots or below one
+    fn contains(&self, path: &Path) -> bool {
+        self.roots.lock().iter().any(|root| path.starts_with(root))
+    }
+}
+
+impl Default for WorkspaceRootsState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Replaces the folders `open_path_external` is allowed to open things in.
+/// Each must exist; they're stored canonicalized so symlinked paths match.
+#[tauri::command]
+pub fn set_workspace_roots(
+    state: tauri::State<'_, WorkspaceRootsState>,
+    roots: Vec<String>,
+) -> Result<(), String> {
+    let roots = roots
+        .iter()
+        .map(|root| fs::canonicalize(root).map_err(|_| format!("Path does not exist: {}", root)))
+        .collect::<Result<Vec<_>, _>>()?;
+    *state.roots.lock() = roots;
+    Ok(())
+}
+
+// Extensions the OS runs rather than opens in a viewer. `.app` bundles are
+// folders on macOS, so this is checked for folders too
+const EXECUTABLE_EXTENSIONS: &[&str] = &[
+    "app", "appimage", "bat", "cmd", "com", "command", "cpl", "desktop", "exe", "jar", "js", "lnk",
+    "msi", "pif", "ps1", "reg", "scr", "sh", "url", "vbs", "wsf",
+];
+
+fn is_executable(path: &Path) -> bool {
+    let by_extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+        .is_some_and(|ext| EXECUTABLE_EXTENSIONS.contains(&ext.as_str()));
+    #[cfg(unix)]
+    let by_mode = {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path)
+            .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+    };
+    #[cfg(not(unix))]
+    let by_mode = false;
+    by_extension || by_mode
+}
+
+/// Opens `path` in its default application, or with `reveal` shows it in
+/// the OS file manager instead. `path` must be absolute and inside one of
+/// the folders given to `set_workspace_roots`, checked after resolving
+/// symlinks. Programs and scripts (an executable bit, or an extension such
+/// as `.exe`, `.app` or `.sh`) can only be revealed, never launched.
+#[tauri::command]
+pub fn open_path_external(
+    roots: tauri::State<'_, WorkspaceRootsState>,
+    path: &str,
+    reveal: Option<bool>,
+) -> Result<(), String> {
+    let target = Path::new(path);
+
+    if !target.is_absolute() {
+        return Err(format!("Path must be absolute: {}", path));
+    }
+
+    // Resolve symlinks and `..` so the OS is handed exactly what was checked
+    let resolved = target
+        .canonicalize()
+        .map_err(|_| format!("Path does not exist: {}", path))?;
+    if !roots.contains(&resolved) {
+        return Err(format!("Path is outside the workspace: {}", path));
+    }
+
+    if reveal.unwrap_or(false) {
+        tauri_plugin_opener::reveal_item_in_dir(&resolved)
+            .map_err(|e| format!("Failed to reveal path: {}", e))
+    } else if is_executable(&resolved) {
+        Err(format!("Refusing to launch an executable: {}", path))
+    } else {
+        tauri_plugin_opener::open_path(&resolved, None::<&str>)
+            .map_err(|e| format!("Failed to open path: {}", e))
+    }
+}
+
+#[tauri::command]
+pub fn expand_path(path: &str) -> Result<String, String> {
+    expand_path_with(path, |key| std::env::var(key).ok())
+}
+
+// Expands a leading `~` or `~user`, then `$VAR`, `${VAR}` and `%VAR%`
+// references. Undefined variables are left in place verbatim (as shells do
+// for unknown `%VAR%`), so a typo stays visible in the result instead of
+// silently collapsing the path. An unknown `~user` is an error because
+// there is no sensible literal fallback.
+fn expand_path_with(path: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String, String> {
+    let (home, rest) = match path.strip_prefix('~') {
+        Some(after) => {
+            let end = after.find(std::path::is_separator).unwrap_or(after.len());
+            let user = &after[..end];
+            let home = if user.is_empty() {
+                lookup("HOME")
+                    .or_else(|| lookup("USERPROFILE"))
+                    .ok_or_else(|| "Could not determine home directory".to_string())?
+            } else {
+                user_home(user).ok_or_else(|| format!("Unknown user: {}", user))?
+            };
+            (home, &after[end..])
+        }
+        None => (String::new(), path),
+    };
+
+    Ok(home + &expand_vars(rest, &lookup))
+}
+
+fn expand_vars(input: &str, lookup: &impl Fn(&str) -> Option<String>) -> String {
+    let is_name_char = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    let mut out = String::with_capacity(input.len());
+    let mut rest = input;
+
+    while let Some(pos) = rest.find(['$', '%']) {
+        out.push_str(&rest[..pos]);
+        let marker = &rest[pos..pos + 1];
+        let after = &rest[pos + 1..];
+
+        // Work out the variable name and how much input the reference spans
+        let reference = if marker == "%" {
+            after
+                .find('%')
+                .map(|end| (&after[..end], end + 1))
+                .filter(|(name, _)| {
+                    !name.is_empty()
+                        && name
+                            .chars()
+                            .all(|c| is_name_char(c) || c == '(' || c == ')')
+                })
+        } else if let Some(braced) = after.strip_prefix('{') {
+            braced
+                .find('}')
+                .map(|end| (&braced[..end], end + 2))
+                .filter(|(name, _)| !name.is_empty() && name.chars().all(is_name_char))
+        } else {
+            let end = after
+                .find(|c: char| !is_name_char(c))
+                .unwrap_or(after.len());
+            Some((&after[..end], end))
+                .filter(|(name, _)| name.chars().next().is_some_and(|c| !c.is_ascii_digit()))
+        };
+
+        match reference.and_then(|(name, len)| lookup(name).map(|value| (value, len))) {
+            Some((value, len)) => {
+                out.push_str(&value);
+                rest = &after[len..];
+            }
+            None => {
+                out.push_str(marker);
+                rest = after;
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(unix)]
+fn user_home(name: &str) -> Option<String> {
+    use std::ffi::{CStr, CString};
+
+    let c_name = CString::new(name).ok()?;
+    let mut buf = vec![0 as libc::c_char; 16 * 1024];
+    let mut pwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result: *mut libc::passwd = std::ptr::null_mut();
+
+    let rc = unsafe {
+        libc::getpwnam_r(
+            c_name.as_ptr(),
+            &mut pwd,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    if rc != 0 || result.is_null() {
+        return None;
+    }
+
+    let dir = unsafe { CStr::from_ptr(pwd.pw_dir) };
+    Some(dir.to_string_lossy().into_owned())
+}
+
+#[cfg(not(unix))]
+fn user_home(_name: &str) -> Option<String> {
+    None
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentKind {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// Text in some other 8-bit encoding (Latin-1, Windows-1252, ...)
+    Text,
+    Binary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BinaryCheck {
+    pub is_binary: bool,
+    pub kind: ContentKind,
+}
+
+#[tauri::command]
+pub fn is_binary_file(path: &str) -> Result<BinaryCheck, String> {
+    let file_path = Path::new(path);
+
+    if !file_path.is_file() {
+        return Err(format!("Path is not a file: {}", path));
+    }
+
+    let mut head = Vec::with_capacity(BINARY_SNIFF_LEN);
+    fs::File::open(file_path)
+        .and_then(|f| f.take(BINARY_SNIFF_LEN as u64).read_to_end(&mut head))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    let kind = classify_content(&head);
+    Ok(BinaryCheck {
+        is_binary: kind == ContentKind::Binary,
+        kind,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MimeSource {
+    Content,
+    Extension,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MimeType {
+    pub mime: String,
+    pub source: MimeSource,
+}
+
+/// Magic bytes win over the extension, so a PNG saved as `.txt` is still
+/// `image/png`. Files with neither come back as `text/plain` or
+/// `application/octet-stream` depending on whether the content looks binary.
+#[tauri::command]
+pub fn file_mime_type(path: &str) -> Result<MimeType, String> {
+    let file_path = Path::new(path);
+
+    if !file_path.is_file() {
+        return Err(format!("Path is not a file: {}", path));
+    }
+
+    let mut head = Vec::with_capacity(BINARY_SNIFF_LEN);
+    fs::File::open(file_path)
+        .and_then(|f| f.take(BINARY_SNIFF_LEN as u64).read_to_end(&mut head))
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+
+    if let Some(kind) = infer::get(&head) {
+        return Ok(MimeType {
+            mime: kind.mime_type().to_string(),
+            source: MimeSource::Content,
+        });
+    }
+
+    if let Some(mime) = mime_guess::from_path(file_path).first_raw() {
+        return Ok(MimeType {
+            mime: mime.to_string(),
+            source: MimeSource::Extension,
+        });
+    }
+
+    let mime = if looks_binary(&head) {
+        "application/octet-stream"
+    } else {
+        "text/plain"
+    };
+    Ok(MimeType {
+        mime: mime.to_string(),
+        source: MimeSource::Content,
+    })
+}
+
+pub(crate) fn looks_binary(bytes: &[u8]) -> bool {
+    classify_content(bytes) == ContentKind::Binary
+}
+
+pub(crate) fn classify_content(bytes: &[u8]) -> ContentKind {
+    let head = &bytes[..bytes.len().min(BINARY_SNIFF_LEN)];
+
+    match head {
+        [0xEF, 0xBB, 0xBF, ..] => return ContentKind::Utf8,
+        [0xFF, 0xFE, ..] => return ContentKind::Utf16Le,
+        [0xFE, 0xFF, ..] => return ContentKind::Utf16Be,
+        _ => {}
+    }
+
+    if head.contains(&0) {
+        // BOM-less UTF-16 puts NUL in the high byte of nearly every ASCII
+        // character, so the zeros cluster on one parity
+        let pairs = head.len() / 2;
+        let even_zeros = head.iter().step_by(2).filter(|&&b| b == 0).count();
+        let odd_zeros = head.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+        if pairs > 0 {
+            if odd_zeros * 10 >= pairs * 7 && even_zeros * 10 <= pairs {
+                return ContentKind::Utf16Le;
+            }
+            if even_zeros * 10 >= pairs * 7 && odd_zeros * 10 <= pairs {
+                return ContentKind::Utf16Be;
+            }
+        }
+        return ContentKind::Binary;
+    }
+
+    // Control characters other than common whitespace, backspace and escape
+    // (used by terminal logs) are rare in text
+    let suspicious = head
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0C | 0x08 | 0x1B))
+        .count();
+    if suspicious * 10 > head.len() {
+        return ContentKind::Binary;
+    }
+
+    match std::str::from_utf8(head) {
+        Ok(_) => ContentKind::Utf8,
+        // The sniff window can cut a multi-byte character in half
+        Err(e) if e.error_len().is_none() => ContentKind::Utf8,
+        Err(_) => ContentKind::Text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_util::TempDir;
+
+    #[test]
+    fn backups_in_the_same_millisecond_are_all_kept() {
+        let dir = TempDir::new("backups");
+        let file = dir.join("notes.txt");
+        let history = dir.join(BACKUP_DIR);
+        for content in ["one", "two", "three"] {
+            fs::write(&file, content).unwrap();
+            create_backup(&file, &history, "notes.txt", 42).unwrap();
+        }
+        assert_eq!(
+            fs::read_to_string(history.join("notes.txt.42-1.bak")).unwrap(),
+            "two"
+        );
+
+        // The oldest goes first, suffixed ones ordered after the plain name
+        prune_backups(&history, "notes.txt", 2);
+        let mut left: Vec<_> = fs::read_dir(&history)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["notes.txt.42-1.bak", "notes.txt.42-2.bak"]);
+    }
+
+    #[test]
+    fn locked_files_refuse_writes_until_unlocked() {
+        let dir = TempDir::new("locks");
+        let path = dir.join("viewed.txt");
+        fs::write(&path, "original").unwrap();
+        let path_str = path.to_str().unwrap();
+
+        let locks = FileLockState::new();
+        locks.lock(path_str).unwrap();
+        let err = write_file_with(&locks, path_str, "changed", None, None, None).unwrap_err();
+        assert!(err.starts_with("Locked:"), "{}", err);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "original");
+
+        assert!(locks.unlock(path_str).unwrap());
+        write_file_with(&locks, path_str, "changed", None, None, None).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "changed");
+    }
+
+    #[test]
+    fn is_executable_flags_programs_and_scripts() {
+        let dir = TempDir::new("executable");
+        for name in ["setup.EXE", "install.sh", "Tool.app"] {
+            fs::write(dir.join(name), "").unwrap();
+            assert!(is_executable(&dir.join(name)), "{}", name);
+        }
+        fs::write(dir.join("notes.txt"), "").unwrap();
+        assert!(!is_executable(&dir.join("notes.txt")));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let script = dir.join("run");
+            fs::write(&script, "").unwrap();
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+            assert!(is_executable(&script));
+        }
+    }
+
+    #[test]
+    fn read_file_auto_decodes_every_zstd_frame() {
+        let dir = TempDir::new("zstd-frames");
+        let level = ruzstd::encoding::CompressionLevel::Fastest;
+        let mut bytes = ruzstd::encoding::compress_to_vec(&b"first\n"[..], level);
+        bytes.extend(ruzstd::encoding::compress_to_vec(&b"second\n"[..], level));
+        let path = dir.join("log.zst");
+        fs::write(&path, bytes).unwrap();
+
+        let file = read_file_auto(path.to_string_lossy().to_string()).unwrap();
+        assert_eq!(file.content, "first\nsecond\n");
+        assert!(matches!(file.compression, Some(CompressionFormat::Zstd)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn create_symlink_links_files_and_directories() {
+        let dir = TempDir::new("symlink");
+        fs::write(dir.join("config.json"), "{}").unwrap();
+        fs::create_dir(dir.join("shared")).unwrap();
+
+        let file_link = dir.join("config-link.json");
+        let dir_link = dir.join("shared-link");
+        create_symlink("config.json", file_link.to_str().unwrap()).unwrap();
+        create_symlink(
+            dir.join("shared").to_str().unwrap(),
+            dir_link.to_str().unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(fs::read_link(&file_link).unwrap(), Path::new("config.json"));
+        assert_eq!(fs::read_to_string(&file_link).unwrap(), "{}");
+        assert!(dir_link.is_dir());
+        // Existing paths are never replaced
+        let err = create_symlink("shared", file_link.to_str().unwrap()).unwrap_err();
+        assert!(err.contains("already exists"), "{}", err);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn create_symlink_links_files_and_directories() {
+        let dir = TempDir::new("symlink");
+        fs::write(dir.join("config.json"), "{}").unwrap();
+        fs::create_dir(dir.join("shared")).unwrap();
+
+        let file_link = dir.join("config-link.json");
+        match create_symlink("config.json", file_link.to_str().unwrap()) {
+            Ok(()) => {}
+            // Unprivileged runners can't create symlinks at all
+            Err(err) if err.contains("Developer Mode") => return,
+            Err(err) => panic!("{}", err),
+        }
+        let dir_link = dir.join("shared-link");
+        create_symlink("shared", dir_link.to_str().unwrap()).unwrap();
+
+        assert_eq!(fs::read_to_string(&file_link).unwrap(), "{}");
+        assert!(dir_link.is_dir());
+    }
+
+    #[test]
+    fn write_file_preserves_bom_round_trip() {
+        let dir = TempDir::new("bom");
+        let with_bom = dir.join("with-bom.txt");
+        let original = b"\xEF\xBB\xBFname,value\r\nalpha,1\r\n".to_vec();
+        fs::write(&with_bom, &original).unwrap();
+
+        let details = read_file_detailed(with_bom.to_str().unwrap()).unwrap();
+        assert_eq!(details.bom, Some(Bom::Utf8));
+        assert_eq!(details.content, "name,value\r\nalpha,1\r\n");
+        write_file_with(
+            &FileLockState::new(),
+            with_bom.to_str().unwrap(),
+            &details.content,
+            None,
+            Some(true),
+            None,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&with_bom).unwrap(), original);
+
+        let without_bom = dir.join("without-bom.txt");
+        fs::write(&without_bom, "plain\n").unwrap();
+        let details = read_file_detailed(without_bom.to_str().unwrap()).unwrap();
+        assert_eq!(details.bom, None);
+        write_file_with(
+            &FileLockState::new(),
+            without_bom.to_str().unwrap(),
+            &details.content,
+            None,
+            Some(true),
+            None,
+        )
+        .unwrap();
+        assert_eq!(fs::read(&without_bom).unwrap(), b"plain\n");
+    }
+
+    #[test]
+    fn detect_language_prefers_extension_then_sniffs() {
+        let detect = |path: &str, content: &str| detect_language(Path::new(path), content);
+
+        assert_eq!(detect("/src/App.tsx", ""), Some("typescript"));
+        assert_eq!(detect("/src/MAIN.RS", ""), Some("rust"));
+        assert_eq!(detect("/notes.txt", "#!/bin/bash"), None);
+        assert_eq!(detect("/repo/Dockerfile", "FROM rust"), Some("dockerfile"));
+        assert_eq!(detect("/bin/deploy", "#!/bin/bash\necho hi"), Some("shell"));
+        assert_eq!(
+            detect("/bin/tool", "#!/usr/bin/env -S python3.12 -u"),
+            Some("python")
+        );
+        assert_eq!(
+            detect("/bin/serve", "#!/usr/bin/env node"),
+            Some("javascript")
+        );
+        assert_eq!(detect("/index", "<!DOCTYPE html>\n<html>"), Some("html"));
+        assert_eq!(detect("/README", "Just some text"), None);
+    }
+
+    #[test]
+    fn format_mode_matches_ls() {
+        assert_eq!(format_mode(0o755), "rwxr-xr-x");
+        assert_eq!(format_mode(0o640), "rw-r-----");
+        assert_eq!(format_mode(0o4755), "rwsr-xr-x");
+        assert_eq!(format_mode(0o2644), "rw-r-Sr--");
+        assert_eq!(format_mode(0o1777), "rwxrwxrwt");
+    }
+
+    #[test]
+    fn rename_path_unique_numbers_conflicts() {
+        let dir = TempDir::new("rename-unique");
+        let dest = dir.join("out");
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(dest.join("notes.txt"), "existing").unwrap();
+        fs::write(dest.join("notes (2).txt"), "existing").unwrap();
+        fs::write(dir.join("notes.txt"), "moved").unwrap();
+
+        let renamed = rename_path_unique(
+            dir.join("notes.txt").to_str().unwrap(),
+            dest.join("notes.txt").to_str().unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(Path::new(&renamed), dest.join("notes (3).txt"));
+        assert_eq!(fs::read_to_string(&renamed).unwrap(), "moved");
+        assert_eq!(
+            fs::read_to_string(dest.join("notes.txt")).unwrap(),
+            "existing"
+        );
+        assert_eq!(numbered_name("v1.2", 2, false), "v1.2 (2)");
+        assert_eq!(numbered_name(".env", 2, true), ".env (2)");
+    }
+
+    #[test]
+    fn detect_indent_picks_dominant_style_and_width() {
+        let spaces = detect_indent("fn a() {\n    if b {\n        c();\n    }\n}\n");
+        assert_eq!(spaces.style, Some(IndentStyle::Spaces));
+        assert_eq!(spaces.width, Some(4));
+        assert_eq!(spaces.confidence, 1.0);
+
+        // Comment continuations don't drag the width down to one
+        let commented = detect_indent("/**\n * doc\n */\nfn a() {\n  b();\n}\n");
+        assert_eq!(commented.width, Some(2));
+        assert!(commented.confidence > 0.99);
+
+        let mixed = detect_indent("a\n\tb\n\tc\n  d\n");
+        assert_eq!(mixed.style, Some(IndentStyle::Tabs));
+        assert_eq!(mixed.width, None);
+        assert!(mixed.confidence > 0.6 && mixed.confidence < 0.7);
+
+        let flat = detect_indent("a\n\nb\n");
+        assert_eq!(flat.style, None);
+        assert_eq!(flat.lines_sampled, 3);
+    }
+
+    #[test]
+    fn split_records_handles_trailing_delimiter() {
+        assert_eq!(split_records(b"a\0b c\0", b"\0"), ["a", "b c"]);
+        assert_eq!(split_records(b"a\0\0b", b"\0"), ["a", "", "b"]);
+        assert_eq!(split_records(b"one--two--", b"--"), ["one", "two"]);
+        assert_eq!(split_records(b"no delimiter", b"\0"), ["no delimiter"]);
+        assert!(split_records(b"", b"\0").is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_files_follows_links_once() {
+        let dir = TempDir::new("walk-links");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/main.rs"), "").unwrap();
+        std::os::unix::fs::symlink("..", dir.join("src/up")).unwrap();
+        std::os::unix::fs::symlink("src/main.rs", dir.join("main-link.rs")).unwrap();
+
+        let operations = OperationState::new();
+        let operation = operations.start("walk").unwrap();
+        let walk = |follow_symlinks| {
+            let config = FsConfig {
+                follow_symlinks,
+                ..FsConfig::default()
+            };
+            let mut files = Vec::new();
+            walk_files(&dir, &config, &operation.token(), &mut |path, _| {
+                files.push(path.strip_prefix(&dir).unwrap().to_path_buf());
+                true
+            })
+            .unwrap();
+            files.sort();
+            files
+        };
+
+        assert_eq!(walk(false), [PathBuf::from("src/main.rs")]);
+        // `src/up` leads back to the root, which is not walked again
+        assert_eq!(
+            walk(true),
+            [PathBuf::from("main-link.rs"), PathBuf::from("src/main.rs")]
+        );
+    }
+
+    #[test]
+    fn relative_to_walks_up_and_down() {
+        let relative = |base: &str, target: &str| relative_to(Path::new(base), Path::new(target));
+
+        assert_eq!(
+            relative("/p/src/ui", "/p/lib/util.rs"),
+            Some(PathBuf::from("../../lib/util.rs"))
+        );
+        assert_eq!(
+            relative("/p", "/p/src/main.rs"),
+            Some(PathBuf::from("src/main.rs"))
+        );
+        assert_eq!(relative("/p/src", "/p"), Some(PathBuf::from("..")));
+        assert_eq!(relative("/p", "/p"), Some(PathBuf::from(".")));
+    }
+
+    #[test]
+    fn utf8_head_cuts_on_char_boundary() {
+        assert_eq!(utf8_head(b"short", 10), ("short".to_string(), false));
+        assert_eq!(utf8_head(b"abcdef", 3), ("abc".to_string(), true));
+        // "é" is two bytes; cutting through it drops it
+        assert_eq!(utf8_head("aé".as_bytes(), 2), ("a".to_string(), true));
+        assert_eq!(utf8_head("€x".as_bytes(), 1), (String::new(), true));
+        assert_eq!(utf8_head("€x".as_bytes(), 3), ("€".to_string(), true));
+    }
+
+    #[test]
+    fn minimal_cover_drops_nested_and_duplicate_paths() {
+        let paths = |list: &[&str]| list.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            minimal_cover(paths(&[
+                "/p/src/main.rs",
+                "/p/src",
+                "/p/README.md",
+                "/p/src"
+            ])),
+            paths(&["/p/src", "/p/README.md"])
+        );
+        // A sibling sharing a name prefix isn't inside the other
+        assert_eq!(
+            minimal_cover(paths(&["/p/src", "/p/src-old/a.rs"])),
+            paths(&["/p/src", "/p/src-old/a.rs"])
+        );
+        assert_eq!(minimal_cover(paths(&["/", "/etc/hosts"])), paths(&["/"]));
+    }
+
+    #[test]
+    fn normalize_path_set_canonicalizes_spellings() {
+        let dir = TempDir::new("path-set");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "").unwrap();
+        let dir_str = dir.to_str().unwrap();
+
+        let normalized = normalize_path_set(vec![
+            format!("{}/src/./lib.rs", dir_str),
+            format!("{}/src/../src", dir_str),
+            format!("{}/src/", dir_str),
+            format!("{}/missing/../gone.txt", dir_str),
+        ]);
+
+        let expected_src = fs::canonicalize(dir.join("src")).unwrap();
+        let expected_gone = fs::canonicalize(&dir).unwrap().join("gone.txt");
+        assert_eq!(
+            normalized,
+            [
+                expected_src.to_string_lossy().to_string(),
+                expected_gone.to_string_lossy().to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn write_file_new_never_overwrites() {
+        let dir = TempDir::new("write-new");
+        let path = dir.join("nested/new.txt");
+        let path_str = path.to_str().unwrap();
+
+        write_file_new(path_str, "first").unwrap();
+        let err = write_file_new(path_str, "second").unwrap_err();
+
+        assert!(err.starts_with("Conflict:"), "{}", err);
+        assert!(err.contains(path_str));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "first");
+    }
+
+    #[test]
+    fn render_template_substitutes_variables() {
+        let variables = HashMap::from([
+            ("name".to_string(), "Button".to_string()),
+            ("tag".to_string(), "button".to_string()),
+        ]);
+
+        assert_eq!(
+            render_template(
+                "export function {{name}}() { return <{{ tag }} />; }",
+                &variables
+            )
+            .unwrap(),
+            "export function Button() { return <button />; }"
+        );
+        // Values are inserted verbatim, never re-expanded
+        let nested = HashMap::from([("a".to_string(), "{{b}}".to_string())]);
+        assert_eq!(render_template("{{a}}", &nested).unwrap(), "{{b}}");
+        assert_eq!(
+            render_template("open {{ only", &variables).unwrap(),
+            "open {{ only"
+        );
+
+        let err = render_template("{{name}} {{missing}}", &variables).unwrap_err();
+        assert!(err.contains("missing"), "{}", err);
+    }
+
+    #[test]
+    fn glob_match_handles_wildcards() {
+        assert!(glob_match("target", "target"));
+        assert!(!glob_match("target", "targets"));
+        assert!(glob_match("*.log", "build.log"));
+        assert!(!glob_match("*.log", "build.log.gz"));
+        assert!(glob_match("*cache*", "__pycache__"));
+        assert!(glob_match("?.txt", "a.txt"));
+        assert!(!glob_match("?.txt", "ab.txt"));
+    }
+
+    fn fake_env(key: &str) -> Option<String> {
+        match key {
+            "HOME" => Some("/home/tester".to_string()),
+            "PROJECT" => Some("claude-flow".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn expand_path_replaces_leading_tilde() {
+        assert_eq!(expand_path_with("~", fake_env).unwrap(), "/home/tester");
+        assert_eq!(
+            expand_path_with("~/projects", fake_env).unwrap(),
+            "/home/tester/projects"
+        );
+        // Only a leading tilde refers to the home directory
+        assert_eq!(expand_path_with("a/~/b", fake_env).unwrap(), "a/~/b");
+    }
+
+    #[test]
+    fn expand_path_replaces_home_variable() {
+        assert_eq!(
+            expand_path_with("$HOME/code", fake_env).unwrap(),
+            "/home/tester/code"
+        );
+        assert_eq!(
+            expand_path_with("${HOME}/code/$PROJECT", fake_env).unwrap(),
+            "/home/tester/code/claude-flow"
+        );
+        assert_eq!(
+            expand_path_with("%HOME%\\code", fake_env).unwrap(),
+            "/home/tester\\code"
+        );
+    }
+
+    #[test]
+    fn expand_path_leaves_undefined_variables_literal() {
+        assert_eq!(
+            expand_path_with("$NOPE/src", fake_env).unwrap(),
+            "$NOPE/src"
+        );
+        assert_eq!(
+            expand_path_with("${NOPE}/%NOPE%/100%", fake_env).unwrap(),
+            "${NOPE}/%NOPE%/100%"
+        );
+    }
 }
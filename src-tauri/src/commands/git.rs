@@ -0,0 +1,655 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use git2::{BlameOptions, BranchType, ErrorCode, Repository, Status, StatusOptions};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::diff::{diff_text, DiffHunk};
+use super::fs::{classify_content, looks_binary, ContentKind, MAX_TEXT_FILE_SIZE};
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum GitStatus {
+    Repo {
+        /// `None` when HEAD is detached
+        branch: Option<String>,
+        files: Vec<GitFileStatus>,
+    },
+    NotARepo,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum GitRoot {
+    Repo {
+        /// The working tree root, the folder holding `.git`
+        root: String,
+        /// Where the repository data lives. Differs from `root/.git` for
+        /// linked worktrees and submodules
+        git_dir: String,
+        /// True when `.git` is a file pointing elsewhere
+        linked: bool,
+    },
+    NotARepo,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GitFileStatus {
+    pub path: String,
+    /// Changes in the index (added, modified, deleted, renamed)
+    pub staged: bool,
+    /// Unstaged changes to a tracked file
+    pub modified: bool,
+    pub untracked: bool,
+    pub deleted: bool,
+    pub renamed: bool,
+    pub conflicted: bool,
+}
+
+/// Status of every changed or untracked file in the repository containing
+/// `path`. With `subpath` (relative to the repository root), only entries
+/// below it are reported, which keeps large repositories cheap. Ignored
+/// files are never included.
+#[tauri::command(async)]
+pub fn git_status(path: String, subpath: Option<String>) -> Result<GitStatus, String> {
+    let repo = match Repository::discover(&path) {
+        Ok(repo) => repo,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(GitStatus::NotARepo),
+        Err(e) => return Err(format!("Failed to open repository: {}", e.message())),
+    };
+    // Bare repositories have no files to badge
+    let Some(workdir) = repo.workdir().map(Path::to_path_buf) else {
+        return Ok(GitStatus::NotARepo);
+    };
+
+    let mut options = StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .renames_head_to_index(true);
+    if let Some(subpath) = &subpath {
+        options.pathspec(subpath);
+    }
+
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .map_err(|e| format!("Failed to read git status: {}", e.message()))?;
+
+    let files = statuses
+        .iter()
+        .map(|entry| {
+            let status = entry.status();
+            let path = workdir.join(&*String::from_utf8_lossy(entry.path_bytes()));
+            GitFileStatus {
+                path: path.to_string_lossy().to_string(),
+                staged: status.intersects(
+                    Status::INDEX_NEW
+                        | Status::INDEX_MODIFIED
+                        | Status::INDEX_DELETED
+                        | Status::INDEX_RENAMED
+                        | Status::INDEX_TYPECHANGE,
+                ),
+                modified: status.intersects(Status::WT_MODIFIED | Status::WT_TYPECHANGE),
+                untracked: status.contains(Status::WT_NEW),
+                deleted: status.intersects(Status::INDEX_DELETED | Status::WT_DELETED),
+                renamed: status.intersects(Status::INDEX_RENAMED | Status::WT_RENAMED),
+                conflicted: status.contains(Status::CONFLICTED),
+            }
+        })
+        .collect();
+
+    let branch = if repo.head_detached().unwrap_or(false) {
+        None
+    } else {
+        current_branch(&repo)
+    };
+
+    Ok(GitStatus::Repo { branch, files })
+}
+
+// HEAD names the branch even before its first commit, when `head()` fails
+fn current_branch(repo: &Repository) -> Option<String> {
+    let head = repo.find_reference("HEAD").ok()?;
+    let target = head.symbolic_target().ok()??;
+    Some(
+        target
+            .strip_prefix("refs/heads/")
+            .unwrap_or(target)
+            .to_string(),
+    )
+}
+
+/// The nearest repository above `path` (or at it), found by looking for a
+/// `.git` directory or file in each ancestor. Unlike `git_status` this only
+/// touches the filesystem, so it's cheap enough to call per folder.
+#[tauri::command(async)]
+pub fn find_git_root(path: String) -> Result<GitRoot, String> {
+    let start = Path::new(&path);
+    if !start.exists() {
+        return Err(format!("Path does not exist: {}", path));
+    }
+    let start = if start.is_dir() {
+        start
+    } else {
+        start.parent().unwrap_or(start)
+    };
+
+    for dir in start.ancestors() {
+        let dot_git = dir.join(".git");
+        let Ok(metadata) = fs::metadata(&dot_git) else {
+            continue;
+        };
+
+        let (git_dir, linked) = if metadata.is_dir() {
+            (dot_git, false)
+        } else {
+            (read_gitfile(&dot_git)?, true)
+        };
+        return Ok(GitRoot::Repo {
+            root: dir.to_string_lossy().to_string(),
+            git_dir: git_dir.to_string_lossy().to_string(),
+            linked,
+        });
+    }
+
+    Ok(GitRoot::NotARepo)
+}
+
+// Worktrees and submodules replace `.git` with a `gitdir: <path>` file; a
+// relative path is relative to the folder holding it
+fn read_gitfile(dot_git: &Path) -> Result<PathBuf, String> {
+    let content = fs::read_to_string(dot_git)
+        .map_err(|e| format!("Failed to read {}: {}", dot_git.display(), e))?;
+    let target = content
+        .lines()
+        .find_map(|line| line.strip_prefix("gitdir:"))
+        .map(str::trim)
+        .filter(|target| !target.is_empty())
+        .ok_or_else(|| format!("Invalid gitfile format: {}", dot_git.display()))?;
+
+    let base = dot_git.parent().unwrap_or(Path::new(""));
+    let git_dir = base.join(target);
+    if !git_dir.is_dir() {
+        return Err(format!(
+            "Gitfile points to a missing directory: {}",
+            git_dir.display()
+        ));
+    }
+    Ok(git_dir)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum GitBlame {
+    Blame {
+        lines: Vec<BlameLine>,
+    },
+    /// The file isn't in the index (or, with a revision, in that commit)
+    NotTracked,
+    /// No repository, or `path` is outside its working tree
+    NotARepo,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlameLine {
+    /// 1-based
+    pub line: usize,
+    pub commit: String,
+    pub author: Option<String>,
+    /// Milliseconds since the Unix epoch
+    pub time: i64,
+}
+
+/// Who last changed each line of `path`, as of HEAD or `revision` (anything
+/// `git rev-parse` accepts). Uncommitted edits aren't reflected. Pass
+/// `start_line` / `end_line` (1-based, inclusive) to blame only the lines in
+/// view; large files are much cheaper that way.
+#[tauri::command(async)]
+pub fn git_blame(
+    path: String,
+    revision: Option<String>,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+) -> Result<GitBlame, String> {
+    let backwards = matches!((start_line, end_line), (Some(start), Some(end)) if end < start);
+    if start_line == Some(0) || end_line == Some(0) || backwards {
+        return Err("Invalid line range".to_string());
+    }
+
+    let repo = match discover_for_file(Path::new(&path)) {
+        Ok(repo) => repo,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(GitBlame::NotARepo),
+        Err(e) => return Err(format!("Failed to open repository: {}", e.message())),
+    };
+    let Some(workdir) = repo.workdir() else {
+        return Ok(GitBlame::NotARepo);
+    };
+    if !Path::new(&path).is_file() {
+        return Err(format!("File does not exist: {}", path));
+    }
+    let Some(relative) = repo_relative(workdir, Path::new(&path)) else {
+        return Ok(GitBlame::NotARepo);
+    };
+
+    let mut options = BlameOptions::new();
+    if let Some(revision) = &revision {
+        let commit = repo
+            .revparse_single(revision)
+            .and_then(|object| object.peel_to_commit())
+            .map_err(|_| format!("Unknown revision: {}", revision))?;
+        options.newest_commit(commit.id());
+    } else {
+        let index = repo
+            .index()
+            .map_err(|e| format!("Failed to read index: {}", e.message()))?;
+        if index.get_path(&relative, 0).is_none() {
+            return Ok(GitBlame::NotTracked);
+        }
+    }
+    if let Some(start) = start_line {
+        options.min_line(start);
+    }
+    if let Some(end) = end_line {
+        options.max_line(end);
+    }
+
+    let blame = match repo.blame_file(&relative, Some(&mut options)) {
+        Ok(blame) => blame,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(GitBlame::NotTracked),
+        Err(e) => return Err(format!("Failed to blame file: {}", e.message())),
+    };
+
+    let first = start_line.unwrap_or(1);
+    let last = end_line.unwrap_or(usize::MAX);
+    let mut lines = Vec::new();
+    for hunk in blame.iter() {
+        let commit = hunk.final_commit_id().to_string();
+        let signature = hunk.final_signature();
+        let author = signature
+            .as_ref()
+            .and_then(|signature| signature.name().ok())
+            .map(String::from);
+        let time = signature.map_or(0, |signature| signature.when().seconds() * 1000);
+
+        let start = hunk.final_start_line();
+        for line in start..start + hunk.lines_in_hunk() {
+            if (first..=last).contains(&line) {
+                lines.push(BlameLine {
+                    line,
+                    commit: commit.clone(),
+                    author: author.clone(),
+                    time,
+                });
+            }
+        }
+    }
+
+    Ok(GitBlame::Blame { lines })
+}
+
+// Discovered from the folder holding `path` rather than `path` itself, which
+// would be resolved: a symlink belongs to the repository it sits in, since
+// git tracks the link and not its target
+fn discover_for_file(path: &Path) -> Result<Repository, git2::Error> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => Repository::discover(parent),
+        _ => Repository::discover("."),
+    }
+}
+
+// `path` relative to the working tree, or None if it lies outside. The
+// parent is canonicalized rather than the file, which may have been deleted,
+// so a symlinked checkout still matches
+fn repo_relative(workdir: &Path, path: &Path) -> Option<PathBuf> {
+    let workdir = workdir.canonicalize().ok()?;
+    let mut dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => std::env::current_dir().ok()?,
+    };
+    // A file looked up in history may be gone, folders and all, so only the
+    // part that still exists is canonicalized
+    let mut missing = vec![path.file_name()?.to_os_string()];
+    let mut file = loop {
+        if let Ok(canonical) = dir.canonicalize() {
+            break canonical;
+        }
+        missing.push(dir.file_name()?.to_os_string());
+        dir = dir.parent()?.to_path_buf();
+    };
+    for name in missing.into_iter().rev() {
+        file.push(name);
+    }
+    file.strip_prefix(&workdir).ok().map(Path::to_path_buf)
+}
+
+// The repository's own git directory, and the one its refs and packed-refs
+// live in; they differ for linked worktrees
+pub(crate) fn git_dirs(path: &str) -> Result<(PathBuf, PathBuf), String> {
+    let repo = match Repository::discover(path) {
+        Ok(repo) => repo,
+        Err(e) if e.code() == ErrorCode::NotFound => {
+            return Err(format!("Not a git repository: {}", path))
+        }
+        Err(e) => return Err(format!("Failed to open repository: {}", e.message())),
+    };
+    Ok((repo.path().to_path_buf(), repo.commondir().to_path_buf()))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum GitFileDiff {
+    /// No hunks when the file is unchanged
+    Text {
+        hunks: Vec<DiffHunk>,
+    },
+    Binary,
+    /// Absent from the side compared against (untracked, or not yet
+    /// committed), so every line is new
+    NewFile,
+    /// No repository, or `path` is outside its working tree
+    NotARepo,
+}
+
+/// How `path` differs from git, in the same hunks `diff_files` returns, for
+/// the editor's change gutter. By default the working tree file is compared
+/// with HEAD; `staged: true` compares the index with HEAD and
+/// `staged: false` the working tree with the index.
+#[tauri::command(async)]
+pub fn git_file_diff(path: String, staged: Option<bool>) -> Result<GitFileDiff, String> {
+    let repo = match discover_for_file(Path::new(&path)) {
+        Ok(repo) => repo,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(GitFileDiff::NotARepo),
+        Err(e) => return Err(format!("Failed to open repository: {}", e.message())),
+    };
+    let Some(workdir) = repo.workdir() else {
+        return Ok(GitFileDiff::NotARepo);
+    };
+    let Some(relative) = repo_relative(workdir, Path::new(&path)) else {
+        return Ok(GitFileDiff::NotARepo);
+    };
+
+    let index = repo
+        .index()
+        .map_err(|e| format!("Failed to read index: {}", e.message()))?;
+    let index_blob = || -> Result<Option<Vec<u8>>, String> {
+        let Some(entry) = index.get_path(&relative, 0) else {
+            return Ok(None);
+        };
+        let blob = repo
+            .find_blob(entry.id)
+            .map_err(|e| format!("Failed to read index entry: {}", e.message()))?;
+        Ok(Some(blob.content().to_vec()))
+    };
+    let head_blob = || -> Result<Option<Vec<u8>>, String> {
+        // Unborn HEAD: nothing has been committed yet
+        let Ok(tree) = repo.head().and_then(|head| head.peel_to_tree()) else {
+            return Ok(None);
+        };
+        let Ok(entry) = tree.get_path(&relative) else {
+            return Ok(None);
+        };
+        let blob = repo
+            .find_blob(entry.id())
+            .map_err(|e| format!("Failed to read committed file: {}", e.message()))?;
+        Ok(Some(blob.content().to_vec()))
+    };
+    let workdir_file = || -> Result<Option<Vec<u8>>, String> {
+        // Git stores a symlink as its target path, so that's what to compare
+        let link = fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_symlink());
+        if link {
+            let target = fs::read_link(&path).map_err(|e| format!("Failed to read link: {}", e))?;
+            return Ok(Some(target.to_string_lossy().into_owned().into_bytes()));
+        }
+        match fs::read(Path::new(&path)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read file: {}", e)),
+        }
+    };
+
+    let (old, new) = match staged {
+        None => (head_blob()?, workdir_file()?),
+        Some(true) => (head_blob()?, index_blob()?),
+        Some(false) => (index_blob()?, workdir_file()?),
+    };
+    let (old, new) = match (old, new) {
+        (None, Some(_)) => return Ok(GitFileDiff::NewFile),
+        (None, None) => return Err(format!("File does not exist: {}", path)),
+        // Deleted on the newer side: every line is removed
+        (Some(old), new) => (old, new.unwrap_or_default()),
+    };
+
+    for side in [&old, &new] {
+        if side.len() as u64 > MAX_TEXT_FILE_SIZE {
+            return Err(format!(
+                "File is too large to diff ({} bytes, limit is {}): {}",
+                side.len(),
+                MAX_TEXT_FILE_SIZE,
+                path
+            ));
+        }
+    }
+    if looks_binary(&old) || looks_binary(&new) {
+        return Ok(GitFileDiff::Binary);
+    }
+    let (Ok(old), Ok(new)) = (String::from_utf8(old), String::from_utf8(new)) else {
+        return Ok(GitFileDiff::Binary);
+    };
+
+    Ok(GitFileDiff::Text {
+        hunks: diff_text(&old, &new),
+    })
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum GitRevisionFile {
+    Text {
+        content: String,
+    },
+    Binary {
+        /// Base64-encoded bytes
+        data: String,
+    },
+    /// Not in that revision's tree, or not a file there
+    Missing,
+    NotARepo,
+}
+
+/// The content `path` had at `revision` (anything `git rev-parse` accepts,
+/// such as a commit, branch, tag or `HEAD~2`), read from the object database
+/// so the working tree is untouched. `path` needn't exist any more. Content
+/// that isn't UTF-8 text comes back base64-encoded.
+#[tauri::command(async)]
+pub fn git_show_file(path: String, revision: String) -> Result<GitRevisionFile, String> {
+    // Discovery needs a path that exists
+    let Some(start) = Path::new(&path).ancestors().find(|dir| dir.exists()) else {
+        return Ok(GitRevisionFile::NotARepo);
+    };
+    let repo = match Repository::discover(start) {
+        Ok(repo) => repo,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(GitRevisionFile::NotARepo),
+        Err(e) => return Err(format!("Failed to open repository: {}", e.message())),
+    };
+    let Some(workdir) = repo.workdir() else {
+        return Ok(GitRevisionFile::NotARepo);
+    };
+    let Some(relative) = repo_relative(workdir, Path::new(&path)) else {
+        return Ok(GitRevisionFile::Missing);
+    };
+
+    let tree = repo
+        .revparse_single(&revision)
+        .and_then(|object| object.peel_to_tree())
+        .map_err(|_| format!("Unknown revision: {}", revision))?;
+    let Ok(entry) = tree.get_path(&relative) else {
+        return Ok(GitRevisionFile::Missing);
+    };
+    let Ok(blob) = repo.find_blob(entry.id()) else {
+        return Ok(GitRevisionFile::Missing);
+    };
+
+    if blob.size() as u64 > MAX_TEXT_FILE_SIZE {
+        return Err(format!(
+            "File is too large to show ({} bytes, limit is {}): {}",
+            blob.size(),
+            MAX_TEXT_FILE_SIZE,
+            path
+        ));
+    }
+    let bytes = blob.content();
+    if classify_content(bytes) == ContentKind::Utf8 {
+        if let Ok(content) = std::str::from_utf8(bytes) {
+            return Ok(GitRevisionFile::Text {
+                content: content.to_string(),
+            });
+        }
+    }
+    Ok(GitRevisionFile::Binary {
+        data: BASE64.encode(bytes),
+    })
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum GitBranches {
+    Repo {
+        /// `None` when HEAD is detached
+        current: Option<String>,
+        branches: Vec<GitBranch>,
+    },
+    NotARepo,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GitBranch {
+    /// Short name, e.g. `main` or `origin/main`
+    pub name: String,
+    pub remote: bool,
+    pub current: bool,
+    /// Short name of the upstream, for local branches that track one
+    pub upstream: Option<String>,
+    /// Commits on the branch that the upstream lacks, and the reverse
+    pub ahead: Option<usize>,
+    pub behind: Option<usize>,
+}
+
+/// Local branches of the repository containing `path`, sorted by name, plus
+/// remote-tracking ones with `include_remote`. Ahead/behind counts are
+/// against each branch's upstream and absent where there is none.
+#[tauri::command(async)]
+pub fn git_branches(path: String, include_remote: Option<bool>) -> Result<GitBranches, String> {
+    let repo = match Repository::discover(&path) {
+        Ok(repo) => repo,
+        Err(e) if e.code() == ErrorCode::NotFound => return Ok(GitBranches::NotARepo),
+        Err(e) => return Err(format!("Failed to open repository: {}", e.message())),
+    };
+
+    let current = if repo.head_detached().unwrap_or(false) {
+        None
+    } else {
+        current_branch(&repo)
+    };
+    let filter = if include_remote.unwrap_or(false) {
+        None
+    } else {
+        Some(BranchType::Local)
+    };
+    let listed = repo
+        .branches(filter)
+        .map_err(|e| format!("Failed to list branches: {}", e.message()))?;
+
+    let mut branches = Vec::new();
+    for entry in listed {
+        let (branch, kind) =
+            entry.map_err(|e| format!("Failed to list branches: {}", e.message()))?;
+        // Names that aren't UTF-8 can't be shown or checked out from the UI
+        let Ok(Some(name)) = branch.name().map(|name| name.map(String::from)) else {
+            continue;
+        };
+        let remote = kind == BranchType::Remote;
+        // `origin/HEAD` only points at another remote branch
+        if remote && name.ends_with("/HEAD") {
+            continue;
+        }
+
+        let upstream = branch.upstream().ok();
+        let upstream_name = upstream
+            .as_ref()
+            .and_then(|upstream| upstream.name().ok().flatten().map(String::from));
+        let counts = upstream.as_ref().and_then(|upstream| {
+            let local = branch.get().target()?;
+            let upstream = upstream.get().target()?;
+            repo.graph_ahead_behind(local, upstream).ok()
+        });
+
+        branches.push(GitBranch {
+            current: !remote && current.as_deref() == Some(name.as_str()),
+            name,
+            remote,
+            upstream: upstream_name,
+            ahead: counts.map(|(ahead, _)| ahead),
+            behind: counts.map(|(_, behind)| behind),
+        });
+    }
+    branches.sort_by(|a, b| (a.remote, &a.name).cmp(&(b.remote, &b.name)));
+
+    Ok(GitBranches::Repo { current, branches })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_util::TempDir;
+
+    fn commit_file(dir: &Path, name: &str, content: &str) {
+        let repo = Repository::init(dir).unwrap();
+        fs::write(dir.join(name), content).unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(name)).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+    }
+
+    #[test]
+    fn git_blame_accepts_open_ended_ranges() {
+        let dir = TempDir::new("blame-range");
+        commit_file(&dir, "a.txt", "one\ntwo\nthree\n");
+        let path = dir.join("a.txt").to_string_lossy().to_string();
+
+        let lines = |start, end| match git_blame(path.clone(), None, start, end) {
+            Ok(GitBlame::Blame { lines }) => lines.iter().map(|l| l.line).collect::<Vec<_>>(),
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(lines(Some(2), None), [2, 3]);
+        assert_eq!(lines(None, Some(2)), [1, 2]);
+        assert_eq!(lines(Some(2), Some(2)), [2]);
+        assert!(git_blame(path.clone(), None, Some(3), Some(2)).is_err());
+        assert!(git_blame(path, None, Some(0), None).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_to_outside_file_belongs_to_the_repo() {
+        let dir = TempDir::new("outside-link");
+        let outside = TempDir::new("outside-link-target");
+        commit_file(&dir, "a.txt", "one\n");
+        fs::write(outside.join("b.txt"), "two\n").unwrap();
+        let link = dir.join("b.txt");
+        std::os::unix::fs::symlink(outside.join("b.txt"), &link).unwrap();
+        let path = link.to_string_lossy().to_string();
+
+        assert!(matches!(
+            git_blame(path.clone(), None, None, None),
+            Ok(GitBlame::NotTracked)
+        ));
+        assert!(matches!(
+            git_file_diff(path, None),
+            Ok(GitFileDiff::NewFile)
+        ));
+    }
+}
@@ -1,2 +1,12 @@
+pub mod archive;
+pub mod diff;
 pub mod fs;
+pub mod git;
+pub mod operation;
+pub mod process;
+pub mod projects;
 pub mod pty;
+pub mod search;
+#[cfg(test)]
+mod test_util;
+pub mod watch;
@@ -0,0 +1,96 @@
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Registry of long-running operations that can be cancelled from the
+/// frontend. The caller picks the operation ID up front and passes it to the
+/// command, so it can call `cancel_operation` while the command is running.
+pub struct OperationState {
+    running: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl OperationState {
+    pub fn new() -> Self {
+        Self {
+            running: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn start(&self, operation_id: &str) -> Result<OperationGuard<'_>, String> {
+        let mut running = self.running.lock();
+        if running.contains_key(operation_id) {
+            return Err(format!("Operation already running: {}", operation_id));
+        }
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        running.insert(operation_id.to_string(), cancelled.clone());
+
+        Ok(OperationGuard {
+            state: self,
+            operation_id: operation_id.to_string(),
+            token: CancelToken(cancelled),
+        })
+    }
+
+    // Returns whether an operation with that ID was running
+    pub(crate) fn cancel(&self, operation_id: &str) -> bool {
+        match self.running.lock().get(operation_id) {
+            Some(cancelled) => {
+                cancelled.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Default for OperationState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Keeps an operation registered for as long as it's alive.
+pub(crate) struct OperationGuard<'a> {
+    state: &'a OperationState,
+    operation_id: String,
+    token: CancelToken,
+}
+
+impl OperationGuard<'_> {
+    pub(crate) fn token(&self) -> CancelToken {
+        self.token.clone()
+    }
+}
+
+impl Drop for OperationGuard<'_> {
+    fn drop(&mut self) {
+        self.state.running.lock().remove(&self.operation_id);
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+pub(crate) const CANCELLED: &str = "Operation cancelled";
+
+/// Returns whether an operation with that ID was running.
+#[tauri::command]
+pub fn cancel_operation(state: tauri::State<'_, OperationState>, operation_id: String) -> bool {
+    state.cancel(&operation_id)
+}
@@ -0,0 +1,615 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, Signal, System, UpdateKind};
+use tauri::{AppHandle, Manager};
+
+use super::fs::write_atomic;
+use super::pty::{login_shell_path, PtyState};
+
+#[derive(Debug, Serialize)]
+pub struct ForegroundProcess {
+    pub pid: u32,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProcessNode {
+    pub pid: u32,
+    pub name: String,
+    pub command: Vec<String>,
+    pub children: Vec<ProcessNode>,
+}
+
+/// Returns the PTY's child process with everything running beneath it.
+///
+/// The tree comes from a single snapshot of the process table, so processes
+/// that start or exit during the call may be missing. On Windows the child
+/// is the program hosted by ConPTY, and parent links can outlive a parent
+/// whose PID has since been reused. On macOS, processes owned by other users
+/// report no command line.
+#[tauri::command]
+pub fn get_pty_process_tree(
+    state: tauri::State<'_, PtyState>,
+    pty_id: String,
+) -> Result<ProcessNode, String> {
+    let root = Pid::from_u32(state.child_pid(&pty_id)?);
+
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing().with_cmd(UpdateKind::Always),
+    );
+
+    if system.process(root).is_none() {
+        return Err(format!("Process is no longer running: {}", root));
+    }
+
+    let children = children_by_parent(&system);
+    Ok(build_node(&system, &children, root, &mut HashSet::new()))
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResourceUsage {
+    pub pid: u32,
+    /// Percent of one core, so it can exceed 100 on multi-core machines
+    pub cpu_percent: f32,
+    /// Resident memory in bytes
+    pub memory_bytes: u64,
+    /// Number of processes the totals cover
+    pub processes: usize,
+}
+
+/// Samples CPU and resident memory of the PTY's child, summed over its
+/// descendants too with `include_descendants`. CPU is measured over a short
+/// interval, so the call takes a fraction of a second.
+///
+/// Values are approximate and platform-dependent: processes starting or
+/// exiting during the sample are missed, and memory accounting differs
+/// between Linux, macOS and Windows.
+#[tauri::command(async)]
+pub fn get_pty_resource_usage(
+    state: tauri::State<'_, PtyState>,
+    pty_id: String,
+    include_descendants: Option<bool>,
+) -> Result<ResourceUsage, String> {
+    let root = Pid::from_u32(state.child_pid(&pty_id)?);
+    let refresh = ProcessRefreshKind::nothing().with_cpu().with_memory();
+
+    let mut system = System::new();
+    system.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh);
+    if system.process(root).is_none() {
+        return Err(format!("Process is no longer running: {}", root));
+    }
+
+    let mut pids = vec![root];
+    if include_descendants.unwrap_or(false) {
+        let children = children_by_parent(&system);
+        let mut i = 0;
+        while i < pids.len() {
+            for child in children.get(&pids[i]).into_iter().flatten() {
+                if !pids.contains(child) {
+                    pids.push(*child);
+                }
+            }
+            i += 1;
+        }
+    }
+
+    // CPU usage is the difference between two refreshes
+    std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+    system.refresh_processes_specifics(ProcessesToUpdate::Some(&pids), true, refresh);
+
+    let sampled: Vec<_> = pids.iter().filter_map(|pid| system.process(*pid)).collect();
+    Ok(ResourceUsage {
+        pid: root.as_u32(),
+        cpu_percent: sampled.iter().map(|p| p.cpu_usage()).sum(),
+        memory_bytes: sampled.iter().map(|p| p.memory()).sum(),
+        processes: sampled.len(),
+    })
+}
+
+fn children_by_parent(system: &System) -> HashMap<Pid, Vec<Pid>> {
+    let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+    for (pid, process) in system.processes() {
+        if let Some(parent) = process.parent() {
+            children.entry(parent).or_default().push(*pid);
+        }
+    }
+    children
+}
+
+/// Returns the program in the PTY's foreground (`vim`, `node`, or the shell
+/// itself at a prompt). Always `None` on Windows, where ConPTY exposes no
+/// foreground process group.
+#[tauri::command]
+pub fn get_pty_foreground(
+    state: tauri::State<'_, PtyState>,
+    pty_id: String,
+) -> Result<Option<ForegroundProcess>, String> {
+    Ok(state.foreground_pgid(&pty_id)?.and_then(foreground_process))
+}
+
+// Current working directory of a process, where the OS lets us read it
+pub(crate) fn process_cwd(pid: u32) -> Option<String> {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_cwd(UpdateKind::Always),
+    );
+    system
+        .process(pid)?
+        .cwd()
+        .map(|cwd| cwd.to_string_lossy().to_string())
+}
+
+// A process group is named after its leader, whose PID equals the group ID
+pub(crate) fn foreground_process(pgid: u32) -> Option<ForegroundProcess> {
+    let pid = Pid::from_u32(pgid);
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing(),
+    );
+
+    system.process(pid).map(|p| ForegroundProcess {
+        pid: pgid,
+        name: p.name().to_string_lossy().to_string(),
+    })
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileHolder {
+    pub pid: u32,
+    pub name: String,
+}
+
+/// Processes that have `path` open, so an "in use" failure can say what is
+/// holding the file. Read from `/proc` on Linux, `lsof` on macOS and the
+/// Restart Manager on Windows. Processes of other users are usually hidden,
+/// and where the platform can't tell the list is simply empty.
+#[tauri::command(async)]
+pub fn processes_using_file(path: String) -> Result<Vec<FileHolder>, String> {
+    let path = Path::new(&path);
+    if !path.exists() {
+        return Err(format!("Path does not exist: {}", path.display()));
+    }
+
+    let mut pids: Vec<Pid> = open_file_pids(path)
+        .into_iter()
+        .map(Pid::from_u32)
+        .collect();
+    pids.sort();
+    pids.dedup();
+
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&pids),
+        true,
+        ProcessRefreshKind::nothing(),
+    );
+    // Anything that exited since is left out
+    Ok(pids
+        .into_iter()
+        .filter_map(|pid| {
+            system.process(pid).map(|process| FileHolder {
+                pid: pid.as_u32(),
+                name: process.name().to_string_lossy().to_string(),
+            })
+        })
+        .collect())
+}
+
+// Every fd link points at the resolved path; fds of other users' processes
+// can't be read, so those are skipped
+#[cfg(target_os = "linux")]
+fn open_file_pids(path: &Path) -> Vec<u32> {
+    let Ok(target) = fs::canonicalize(path) else {
+        return Vec::new();
+    };
+    let Ok(processes) = fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    processes
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let fds = fs::read_dir(entry.path().join("fd")).ok()?;
+            fds.flatten()
+                .any(|fd| fs::read_link(fd.path()).is_ok_and(|link| link == target))
+                .then_some(pid)
+        })
+        .collect()
+}
+
+// lsof exits with 1 when nobody has the file open, so only its output counts
+#[cfg(all(unix, not(target_os = "linux")))]
+fn open_file_pids(path: &Path) -> Vec<u32> {
+    let Ok(output) = std::process::Command::new("lsof")
+        .arg("-t")
+        .arg("--")
+        .arg(path)
+        .stderr(std::process::Stdio::null())
+        .output()
+    else {
+        return Vec::new();
+    };
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().parse().ok())
+        .collect()
+}
+
+#[cfg(windows)]
+fn open_file_pids(path: &Path) -> Vec<u32> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Foundation::{ERROR_MORE_DATA, ERROR_SUCCESS};
+    use windows_sys::Win32::System::RestartManager::{
+        RmEndSession, RmGetList, RmRegisterResources, RmStartSession, CCH_RM_SESSION_KEY,
+        RM_PROCESS_INFO,
+    };
+
+    let Ok(path) = std::path::absolute(path) else {
+        return Vec::new();
+    };
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+
+    let mut session = 0u32;
+    let mut key = [0u16; CCH_RM_SESSION_KEY as usize + 1];
+    if unsafe { RmStartSession(&mut session, 0, key.as_mut_ptr()) } != ERROR_SUCCESS {
+        return Vec::new();
+    }
+
+    let pids = (|| {
+        let files = [wide.as_ptr()];
+        let registered = unsafe {
+            RmRegisterResources(
+                session,
+                1,
+                files.as_ptr(),
+                0,
+                std::ptr::null(),
+                0,
+                std::ptr::null(),
+            )
+        };
+        if registered != ERROR_SUCCESS {
+            return Vec::new();
+        }
+
+        // The list can grow between sizing it and reading it, so retry
+        let mut processes: Vec<RM_PROCESS_INFO> = Vec::new();
+        loop {
+            let mut needed = 0u32;
+            let mut count = processes.len() as u32;
+            let mut reasons = 0u32;
+            let result = unsafe {
+                RmGetList(
+                    session,
+                    &mut needed,
+                    &mut count,
+                    processes.as_mut_ptr(),
+                    &mut reasons,
+                )
+            };
+            match result {
+                ERROR_SUCCESS => {
+                    processes.truncate(count as usize);
+                    return processes
+                        .iter()
+                        .map(|process| process.Process.dwProcessId)
+                        .collect();
+                }
+                ERROR_MORE_DATA => {
+                    processes = vec![unsafe { std::mem::zeroed() }; needed as usize];
+                }
+                _ => return Vec::new(),
+            }
+        }
+    })();
+
+    unsafe { RmEndSession(session) };
+    pids
+}
+
+#[cfg(not(any(unix, windows)))]
+fn open_file_pids(_path: &Path) -> Vec<u32> {
+    Vec::new()
+}
+
+fn build_node(
+    system: &System,
+    children: &HashMap<Pid, Vec<Pid>>,
+    pid: Pid,
+    visited: &mut HashSet<Pid>,
+) -> ProcessNode {
+    visited.insert(pid);
+
+    let (name, command) = system
+        .process(pid)
+        .map(|p| {
+            (
+                p.name().to_string_lossy().to_string(),
+                p.cmd()
+                    .iter()
+                    .map(|arg| arg.to_string_lossy().to_string())
+                    .collect(),
+            )
+        })
+        .unwrap_or_default();
+
+    let mut child_pids: Vec<Pid> = children.get(&pid).cloned().unwrap_or_default();
+    child_pids.sort();
+
+    // PID reuse can in theory produce a parent cycle; never revisit a process
+    let mut nodes = Vec::with_capacity(child_pids.len());
+    for child in child_pids {
+        if !visited.contains(&child) {
+            nodes.push(build_node(system, children, child, visited));
+        }
+    }
+
+    ProcessNode {
+        pid: pid.as_u32(),
+        name,
+        command,
+        children: nodes,
+    }
+}
+
+const DEFAULT_COMMAND_TIMEOUT_MS: u64 = 60_000;
+
+#[derive(Debug, Serialize)]
+pub struct CommandOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` if the process was killed by a signal or timed out
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+}
+
+/// Runs `program` with `args` to completion without a PTY and returns its
+/// stdout and stderr separately. `env` is added to the app's environment,
+/// with PATH taken from the login shell unless `env` sets it. After
+/// `timeout_ms` (default 60 s) the process and anything it started are
+/// killed and whatever output was captured is returned with `timed_out`.
+/// Output written after the process exits, by something it left running in
+/// the background, is only waited for briefly.
+#[tauri::command(async)]
+pub fn run_command(
+    program: String,
+    args: Vec<String>,
+    cwd: Option<String>,
+    env: Option<HashMap<String, String>>,
+    timeout_ms: Option<u64>,
+) -> Result<CommandOutput, String> {
+    use std::process::{Command, Stdio};
+
+    let mut command = Command::new(&program);
+    command
+        .args(&args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if let Some(cwd) = &cwd {
+        command.current_dir(cwd);
+    }
+    if let Some(path) = login_shell_path() {
+        command.env("PATH", path);
+    }
+    command.envs(env.unwrap_or_default());
+    // Its own process group, so a timeout can kill grandchildren that would
+    // otherwise keep the output pipes open
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    // Read both pipes at once so a full stderr can't block the child while
+    // we wait on stdout
+    let stdout = drain_pipe(child.stdout.take());
+    let stderr = drain_pipe(child.stderr.take());
+
+    let deadline = std::time::Instant::now()
+        + std::time::Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_COMMAND_TIMEOUT_MS));
+    let (status, timed_out) = loop {
+        if let Some(status) = child
+            .try_wait()
+            .map_err(|e| format!("Failed to wait for {}: {}", program, e))?
+        {
+            break (Some(status), false);
+        }
+        if std::time::Instant::now() >= deadline {
+            kill_process_group(&mut child);
+            let _ = child.wait();
+            break (None, true);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+
+    // A background grandchild can hold the pipes open long after the child
+    // is gone, so the rest of the output only gets a short grace period
+    let drain_deadline = deadline.min(std::time::Instant::now() + PIPE_DRAIN_GRACE);
+    Ok(CommandOutput {
+        stdout: stdout.collect(drain_deadline),
+        stderr: stderr.collect(drain_deadline),
+        exit_code: status.and_then(|status| status.code()),
+        timed_out,
+    })
+}
+
+const PIPE_DRAIN_GRACE: std::time::Duration = std::time::Duration::from_millis(500);
+
+// Output read so far from a pipe, and a signal once it reaches EOF
+struct DrainedPipe {
+    bytes: std::sync::Arc<Mutex<Vec<u8>>>,
+    done: std::sync::mpsc::Receiver<()>,
+}
+
+impl DrainedPipe {
+    // Waits for EOF until `deadline`, then takes whatever has arrived
+    fn collect(self, deadline: std::time::Instant) -> String {
+        let _ = self
+            .done
+            .recv_timeout(deadline.saturating_duration_since(std::time::Instant::now()));
+        let bytes = self.bytes.lock();
+        String::from_utf8_lossy(&bytes).to_string()
+    }
+}
+
+fn drain_pipe<R: std::io::Read + Send + 'static>(pipe: Option<R>) -> DrainedPipe {
+    let bytes = std::sync::Arc::new(Mutex::new(Vec::new()));
+    let (finished, done) = std::sync::mpsc::channel();
+    let buffer = bytes.clone();
+    std::thread::spawn(move || {
+        if let Some(mut pipe) = pipe {
+            let mut chunk = [0u8; 8192];
+            loop {
+                match pipe.read(&mut chunk) {
+                    Ok(0) => break,
+                    Ok(n) => buffer.lock().extend_from_slice(&chunk[..n]),
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+                    Err(_) => break,
+                }
+            }
+        }
+        let _ = finished.send(());
+    });
+    DrainedPipe { bytes, done }
+}
+
+#[cfg(unix)]
+fn kill_process_group(child: &mut std::process::Child) {
+    // The group ID is the child's PID, see process_group(0) above
+    unsafe {
+        libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill_process_group(child: &mut std::process::Child) {
+    let _ = child.kill();
+}
+
+// Every PTY child is recorded in the app data dir while it runs, so after a
+// crash the next launch can still find shells that were left behind
+const CHILDREN_FILE: &str = "pty-children.json";
+
+// Serializes read-modify-write cycles on the children file
+static CHILDREN_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ChildRecord {
+    pid: u32,
+    /// Seconds since the epoch; guards against the PID being reused by an
+    /// unrelated process
+    start_time: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrphanProcess {
+    pub pid: u32,
+    pub name: String,
+    pub killed: bool,
+}
+
+fn update_children(app_handle: &AppHandle, update: impl FnOnce(&mut Vec<ChildRecord>)) {
+    let Ok(dir) = app_handle.path().app_data_dir() else {
+        return;
+    };
+    let path = dir.join(CHILDREN_FILE);
+
+    let _lock = CHILDREN_LOCK.lock();
+    let mut records: Vec<ChildRecord> = fs::read(&path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default();
+    update(&mut records);
+
+    // Best effort: losing a record only means an orphan can't be reaped
+    if let Ok(json) = serde_json::to_vec(&records) {
+        let _ = fs::create_dir_all(&dir);
+        let _ = write_atomic(&path, &json, false);
+    }
+}
+
+fn start_time(system: &System, pid: u32) -> Option<u64> {
+    system.process(Pid::from_u32(pid)).map(|p| p.start_time())
+}
+
+pub(crate) fn record_child(app_handle: &AppHandle, pid: u32) {
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[Pid::from_u32(pid)]),
+        true,
+        ProcessRefreshKind::nothing(),
+    );
+    let Some(start_time) = start_time(&system, pid) else {
+        return;
+    };
+
+    update_children(app_handle, |records| {
+        records.retain(|record| record.pid != pid);
+        records.push(ChildRecord { pid, start_time });
+    });
+}
+
+pub(crate) fn forget_child(app_handle: &AppHandle, pid: u32) {
+    update_children(app_handle, |records| {
+        records.retain(|record| record.pid != pid);
+    });
+}
+
+/// Finds PTY children from a previous run (e.g. one that crashed before
+/// calling `kill_pty`) that are still alive. Meant to be called at startup;
+/// with `kill` they're sent SIGHUP, like a closed terminal, or terminated
+/// outright where signals aren't supported. A process only counts if both
+/// its PID and start time match what was recorded, and PTYs owned by this
+/// run are never included.
+#[tauri::command]
+pub fn reap_orphans(
+    app_handle: AppHandle,
+    state: tauri::State<'_, PtyState>,
+    kill: bool,
+) -> Vec<OrphanProcess> {
+    let mut system = System::new();
+    system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+    let owned = state.child_pids();
+    let mut orphans = Vec::new();
+
+    update_children(&app_handle, |records| {
+        records.retain(|record| {
+            if start_time(&system, record.pid) != Some(record.start_time) {
+                // Exited, or the PID now belongs to something else
+                return false;
+            }
+            if owned.contains(&record.pid) {
+                return true;
+            }
+
+            let process = system.process(Pid::from_u32(record.pid));
+            let killed = kill
+                && process.is_some_and(|p| p.kill_with(Signal::Hangup).unwrap_or_else(|| p.kill()));
+            orphans.push(OrphanProcess {
+                pid: record.pid,
+                name: process
+                    .map(|p| p.name().to_string_lossy().to_string())
+                    .unwrap_or_default(),
+                killed,
+            });
+            !killed
+        });
+    });
+
+    orphans
+}
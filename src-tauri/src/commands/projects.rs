@@ -0,0 +1,513 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::{AppHandle, Manager};
+
+use super::fs::{epoch_millis, write_atomic};
+
+// Kept in the app data dir so every window reads and writes the same list
+const RECENT_PROJECTS_FILE: &str = "recent-projects.json";
+const MAX_RECENT_PROJECTS: usize = 20;
+
+// Serializes read-modify-write cycles on the file across windows
+static RECENT_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecentProject {
+    pub path: String,
+    /// Milliseconds since the Unix epoch
+    pub last_opened: u64,
+}
+
+// Loads the list, applies `update`, drops projects whose folder is gone and
+// saves the result, newest first
+fn update_recent(
+    app_handle: &AppHandle,
+    update: impl FnOnce(&mut Vec<RecentProject>),
+) -> Result<Vec<RecentProject>, String> {
+    let dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to locate app data directory: {}", e))?;
+    let path = dir.join(RECENT_PROJECTS_FILE);
+
+    let _lock = RECENT_LOCK.lock();
+    // A missing or corrupt file just means starting over
+    let mut projects: Vec<RecentProject> = fs::read(&path)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default();
+    update(&mut projects);
+
+    projects.retain(|project| Path::new(&project.path).is_dir());
+    projects.sort_by_key(|project| Reverse(project.last_opened));
+    projects.truncate(MAX_RECENT_PROJECTS);
+
+    let json = serde_json::to_vec_pretty(&projects)
+        .map_err(|e| format!("Failed to serialize recent projects: {}", e))?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    write_atomic(&path, &json, false)?;
+
+    Ok(projects)
+}
+
+/// Moves `path` to the top of the recent projects list (adding it if
+/// needed) and returns the updated list. Only the newest 20 are kept.
+#[tauri::command]
+pub fn add_recent_project(
+    app_handle: AppHandle,
+    path: String,
+) -> Result<Vec<RecentProject>, String> {
+    if !Path::new(&path).is_dir() {
+        return Err(format!("Path is not a directory: {}", path));
+    }
+
+    let last_opened = epoch_millis(SystemTime::now()).unwrap_or(0);
+    update_recent(&app_handle, |projects| {
+        projects.retain(|project| project.path != path);
+        projects.push(RecentProject { path, last_opened });
+    })
+}
+
+/// Recent projects, newest first. Folders that no longer exist are pruned
+/// from the saved list.
+#[tauri::command]
+pub fn get_recent_projects(app_handle: AppHandle) -> Result<Vec<RecentProject>, String> {
+    update_recent(&app_handle, |_| {})
+}
+
+#[tauri::command]
+pub fn clear_recent_projects(app_handle: AppHandle) -> Result<(), String> {
+    update_recent(&app_handle, |projects| projects.clear()).map(|_| ())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectKind {
+    Node,
+    Rust,
+    Python,
+    Go,
+    Unknown,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DetectedProject {
+    pub kind: ProjectKind,
+    /// The marker file that identified it, e.g. `package.json`
+    pub marker: Option<String>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+}
+
+// Checked in order; the first marker present for a kind wins
+const PROJECT_MARKERS: &[(ProjectKind, &str)] = &[
+    (ProjectKind::Node, "package.json"),
+    (ProjectKind::Rust, "Cargo.toml"),
+    (ProjectKind::Python, "pyproject.toml"),
+    (ProjectKind::Python, "setup.py"),
+    (ProjectKind::Python, "requirements.txt"),
+    (ProjectKind::Go, "go.mod"),
+];
+
+/// Project types found from marker files in `path` itself (not below it).
+/// A folder can be several at once, e.g. Node and Rust. Name and version
+/// come from the marker when it states them. Returns a single `unknown`
+/// entry when no marker is found.
+#[tauri::command]
+pub fn detect_project_type(path: &str) -> Result<Vec<DetectedProject>, String> {
+    let dir = Path::new(path);
+    if !dir.is_dir() {
+        return Err(format!("Path is not a directory: {}", path));
+    }
+
+    let mut detected: Vec<DetectedProject> = Vec::new();
+    for &(kind, marker) in PROJECT_MARKERS {
+        if detected.iter().any(|project| project.kind == kind) {
+            continue;
+        }
+        let marker_path = dir.join(marker);
+        if !marker_path.is_file() {
+            continue;
+        }
+
+        let content = fs::read_to_string(&marker_path).unwrap_or_default();
+        let (name, version) = match marker {
+            "package.json" => {
+                let json: serde_json::Value = serde_json::from_str(&content).unwrap_or_default();
+                let field = |key: &str| json.get(key).and_then(|v| v.as_str()).map(String::from);
+                (field("name"), field("version"))
+            }
+            "Cargo.toml" => (
+                toml_string(&content, "package", "name"),
+                toml_string(&content, "package", "version"),
+            ),
+            // PEP 621 first, then Poetry's own table
+            "pyproject.toml" => (
+                toml_string(&content, "project", "name")
+                    .or_else(|| toml_string(&content, "tool.poetry", "name")),
+                toml_string(&content, "project", "version")
+                    .or_else(|| toml_string(&content, "tool.poetry", "version")),
+            ),
+            "go.mod" => (
+                content
+                    .lines()
+                    .find_map(|line| line.trim().strip_prefix("module "))
+                    .map(|module| module.trim().to_string()),
+                None,
+            ),
+            _ => (None, None),
+        };
+
+        detected.push(DetectedProject {
+            kind,
+            marker: Some(marker.to_string()),
+            name,
+            version,
+        });
+    }
+
+    if detected.is_empty() {
+        detected.push(DetectedProject {
+            kind: ProjectKind::Unknown,
+            marker: None,
+            name: None,
+            version: None,
+        });
+    }
+    Ok(detected)
+}
+
+// Reads a plain `key = "value"` string from a TOML table. Enough for
+// manifest names and versions without pulling in a TOML parser; anything
+// fancier (inline tables, `version.workspace = true`) reads as absent
+fn toml_string(content: &str, table: &str, key: &str) -> Option<String> {
+    let mut current = String::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            current = header
+                .trim_start_matches('[')
+                .split(']')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_string();
+            continue;
+        }
+        if current != table {
+            continue;
+        }
+
+        let Some((line_key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if line_key.trim() != key {
+            continue;
+        }
+        let value = value.trim();
+        let quote = value.chars().next().filter(|c| matches!(c, '"' | '\''))?;
+        return value[1..].split(quote).next().map(String::from);
+    }
+
+    None
+}
+
+#[derive(Debug, Serialize)]
+pub struct ResolvedConfig {
+    pub config: serde_json::Value,
+    /// Every file merged in, each base before the files extending it
+    pub files: Vec<String>,
+    /// Package references that aren't on disk, e.g. `eslint:recommended`
+    pub unresolved: Vec<String>,
+}
+
+/// Reads a JSON config (comments and trailing commas allowed, as in
+/// tsconfig) and merges in everything its `extends` names, recursively.
+/// Relative paths are taken from the extending file's folder; anything else
+/// is looked up in `node_modules` the way Node would, falling back to a
+/// package's `tsconfig` field or `tsconfig.json`. Objects merge key by key
+/// with the extending file winning; arrays and other values are replaced
+/// whole, as tsconfig does for `include` and `files`. A cycle is an error.
+#[tauri::command(async)]
+pub fn read_config_resolved(path: String) -> Result<ResolvedConfig, String> {
+    let mut resolved = ResolvedConfig {
+        config: serde_json::Value::Null,
+        files: Vec::new(),
+        unresolved: Vec::new(),
+    };
+    resolved.config = load_config(Path::new(&path), &mut Vec::new(), &mut resolved)?;
+    Ok(resolved)
+}
+
+// `chain` holds the files currently being extended, to spot cycles; a base
+// reached along two separate paths is fine
+fn load_config(
+    path: &Path,
+    chain: &mut Vec<PathBuf>,
+    resolved: &mut ResolvedConfig,
+) -> Result<serde_json::Value, String> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+    if let Some(start) = chain.iter().position(|seen| *seen == canonical) {
+        let cycle: Vec<String> = chain[start..]
+            .iter()
+            .chain([&canonical])
+            .map(|path| path.display().to_string())
+            .collect();
+        return Err(format!("Circular extends: {}", cycle.join(" -> ")));
+    }
+
+    let content = fs::read_to_string(&canonical)
+        .map_err(|e| format!("Failed to read config {}: {}", path.display(), e))?;
+    let mut config: serde_json::Value = serde_json::from_str(&strip_jsonc(&content))
+        .map_err(|e| format!("Failed to parse config {}: {}", path.display(), e))?;
+
+    let extends = match config
+        .as_object_mut()
+        .and_then(|object| object.remove("extends"))
+    {
+        None => Vec::new(),
+        Some(serde_json::Value::String(spec)) => vec![spec],
+        Some(serde_json::Value::Array(specs)) => specs
+            .into_iter()
+            .filter_map(|spec| spec.as_str().map(String::from))
+            .collect(),
+        Some(_) => return Err(format!("Invalid extends in {}", path.display())),
+    };
+
+    let dir = canonical.parent().unwrap_or(Path::new("/")).to_path_buf();
+    let mut merged = serde_json::Value::Object(serde_json::Map::new());
+    chain.push(canonical.clone());
+    for spec in extends {
+        match resolve_extends(&dir, &spec)? {
+            Some(base) => {
+                let base = load_config(&base, chain, resolved)?;
+                merge_config(&mut merged, base);
+            }
+            None => resolved.unresolved.push(spec),
+        }
+    }
+    chain.pop();
+    merge_config(&mut merged, config);
+
+    let file = canonical.to_string_lossy().to_string();
+    if !resolved.files.contains(&file) {
+        resolved.files.push(file);
+    }
+    Ok(merged)
+}
+
+fn resolve_extends(dir: &Path, spec: &str) -> Result<Option<PathBuf>, String> {
+    if spec.starts_with('.') || Path::new(spec).is_absolute() {
+        return config_file(&dir.join(spec)).map(Some).ok_or_else(|| {
+            format!(
+                "Extended config not found: {} (from {})",
+                spec,
+                dir.display()
+            )
+        });
+    }
+
+    for ancestor in dir.ancestors() {
+        let candidate = ancestor.join("node_modules").join(spec);
+        if let Some(found) = config_file(&candidate).or_else(|| package_config(&candidate)) {
+            return Ok(Some(found));
+        }
+    }
+    Ok(None)
+}
+
+// tsconfig lets `extends` leave off the `.json`
+fn config_file(path: &Path) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    let mut with_extension = path.as_os_str().to_owned();
+    with_extension.push(".json");
+    let with_extension = PathBuf::from(with_extension);
+    with_extension.is_file().then_some(with_extension)
+}
+
+fn package_config(dir: &Path) -> Option<PathBuf> {
+    if !dir.is_dir() {
+        return None;
+    }
+    let manifest: serde_json::Value = fs::read(dir.join("package.json"))
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default();
+    let declared = ["tsconfig", "main"]
+        .iter()
+        .filter_map(|key| manifest.get(key)?.as_str())
+        .find(|file| file.ends_with(".json"))
+        .map(|file| dir.join(file));
+
+    declared
+        .into_iter()
+        .chain([dir.join("tsconfig.json")])
+        .find(|path| path.is_file())
+}
+
+fn merge_config(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_config(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+// Drops `//` and `/* */` comments and trailing commas, leaving strings alone,
+// so JSONC parses as plain JSON
+fn strip_jsonc(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        out.push(c);
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut last = '\0';
+                for c in chars.by_ref() {
+                    if last == '*' && c == '/' {
+                        break;
+                    }
+                    last = c;
+                }
+            }
+            // Comments are already gone, so a comma followed only by
+            // whitespace is a trailing one
+            ('}' | ']', _) => {
+                let trimmed = out.trim_end().len();
+                if out[..trimmed].ends_with(',') {
+                    out.truncate(trimmed - 1);
+                }
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_util::TempDir;
+
+    #[test]
+    fn toml_string_reads_table_values() {
+        let manifest = r#"
+[package]
+name = "claude-flow"
+version = '0.1.0' # trailing comment
+
+[dependencies]
+name = "not-this"
+
+[tool.poetry]
+version.workspace = true
+"#;
+
+        assert_eq!(
+            toml_string(manifest, "package", "name").as_deref(),
+            Some("claude-flow")
+        );
+        assert_eq!(
+            toml_string(manifest, "package", "version").as_deref(),
+            Some("0.1.0")
+        );
+        assert_eq!(toml_string(manifest, "dependencies", "version"), None);
+        assert_eq!(toml_string(manifest, "tool.poetry", "version"), None);
+    }
+
+    #[test]
+    fn strip_jsonc_keeps_strings() {
+        let config = r#"{
+  // line comment
+  "url": "http://example.com/*not a comment*/", /* block */
+  "paths": ["a", "b",],
+  "escaped": "quote \" // still a string",
+}"#;
+
+        let value: serde_json::Value = serde_json::from_str(&strip_jsonc(config)).unwrap();
+        assert_eq!(value["url"], "http://example.com/*not a comment*/");
+        assert_eq!(value["paths"], serde_json::json!(["a", "b"]));
+        assert_eq!(value["escaped"], "quote \" // still a string");
+    }
+
+    #[test]
+    fn read_config_resolved_merges_extends() {
+        let dir = TempDir::new("config-extends");
+        fs::create_dir_all(dir.join("node_modules/@scope/base")).unwrap();
+        fs::write(
+            dir.join("node_modules/@scope/base/tsconfig.json"),
+            r#"{ "compilerOptions": { "strict": true, "target": "es2017" }, "include": ["lib"] }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("tsconfig.base.json"),
+            r#"{ "extends": "@scope/base", "compilerOptions": { "target": "es2022" } }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("tsconfig.json"),
+            r#"{ "extends": ["./tsconfig.base", "eslint:recommended"], "include": ["src"] }"#,
+        )
+        .unwrap();
+
+        let resolved =
+            read_config_resolved(dir.join("tsconfig.json").to_string_lossy().to_string()).unwrap();
+        assert_eq!(
+            resolved.config,
+            serde_json::json!({
+                "compilerOptions": { "strict": true, "target": "es2022" },
+                "include": ["src"],
+            })
+        );
+        assert_eq!(resolved.files.len(), 3);
+        assert_eq!(resolved.unresolved, vec!["eslint:recommended"]);
+
+        fs::write(dir.join("a.json"), r#"{ "extends": "./b.json" }"#).unwrap();
+        fs::write(dir.join("b.json"), r#"{ "extends": "./a.json" }"#).unwrap();
+        let err =
+            read_config_resolved(dir.join("a.json").to_string_lossy().to_string()).unwrap_err();
+        assert!(err.starts_with("Circular extends:"), "{}", err);
+    }
+}
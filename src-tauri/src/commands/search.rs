@@ -0,0 +1,503 @@
+use regex::bytes::{NoExpand, Regex, RegexBuilder};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use super::fs::{looks_binary, walk_files, write_atomic, FileLockState, FsConfig, FsConfigState};
+use super::operation::{CancelToken, OperationState};
+
+// Files bigger than this are skipped; they're almost never source
+const MAX_SEARCH_FILE_SIZE: u64 = 2 * 1024 * 1024;
+const DEFAULT_MAX_RESULTS: usize = 10_000;
+// Matching lines longer than this are cut before being sent
+const MAX_LINE_PREVIEW: usize = 500;
+
+// Matches are sent in batches, at most one event per interval unless a
+// batch fills up first, so a hit-heavy search doesn't flood the frontend
+const RESULT_EMIT_INTERVAL: Duration = Duration::from_millis(50);
+const MAX_RESULT_BATCH: usize = 200;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchMatch {
+    pub path: String,
+    /// One-based
+    pub line: usize,
+    /// One-based character column of the first occurrence on the line
+    pub column: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileMatch {
+    /// Byte offsets into the file, end exclusive
+    pub start: usize,
+    pub end: usize,
+    /// One-based
+    pub line: usize,
+    /// One-based character column of `start`
+    pub column: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileSearchResults {
+    pub matches: Vec<FileMatch>,
+    /// Stopped at `max_results` before the end of the file
+    pub truncated: bool,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchResults {
+    pub operation_id: String,
+    pub matches: Vec<SearchMatch>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchDone {
+    pub operation_id: String,
+    pub files_searched: usize,
+    pub matches: usize,
+    /// Stopped at `max_results` before the walk finished
+    pub truncated: bool,
+}
+
+/// Searches text files below `root` for lines containing `query`, emitting
+/// matches as `search-result` events while the walk runs and a final
+/// `search-done` with the totals, which is also the return value. Ignored
+/// names, binary files and files over 2 MB are skipped; `follow_symlinks`
+/// overrides the `FsConfig` setting for this call. Cancel with
+/// `cancel_operation`; a cancelled search returns an error and emits no
+/// `search-done`.
+#[tauri::command(async)]
+#[allow(clippy::too_many_arguments)]
+pub fn search_in_files(
+    app_handle: AppHandle,
+    operations: tauri::State<'_, OperationState>,
+    config: tauri::State<'_, FsConfigState>,
+    root: String,
+    query: String,
+    case_sensitive: Option<bool>,
+    max_results: Option<usize>,
+    follow_symlinks: Option<bool>,
+    operation_id: String,
+) -> Result<SearchDone, String> {
+    let operation = operations.start(&operation_id)?;
+    let root_path = Path::new(&root);
+    if !root_path.is_dir() {
+        return Err(format!("Path is not a directory: {}", root));
+    }
+    if query.is_empty() {
+        return Err("Search query must not be empty".to_string());
+    }
+
+    let matcher = build_matcher(&query, false, case_sensitive)?;
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+
+    let mut batch = Vec::new();
+    let mut last_emit = Instant::now();
+    let mut files_searched = 0;
+    let mut total = 0;
+    let emit_batch = |batch: &mut Vec<SearchMatch>| {
+        if !batch.is_empty() {
+            let _ = app_handle.emit(
+                "search-result",
+                SearchResults {
+                    operation_id: operation_id.clone(),
+                    matches: std::mem::take(batch),
+                },
+            );
+        }
+    };
+
+    let completed = walk_files(
+        root_path,
+        &config.for_walk(follow_symlinks),
+        &operation.token(),
+        &mut |path, metadata| {
+            if metadata.len() > MAX_SEARCH_FILE_SIZE {
+                return true;
+            }
+            let Ok(bytes) = fs::read(path) else {
+                return true;
+            };
+            if looks_binary(&bytes) {
+                return true;
+            }
+            files_searched += 1;
+
+            let content = String::from_utf8_lossy(&bytes);
+            for (index, line) in content.lines().enumerate() {
+                let Some(column) = find_column(line, &matcher) else {
+                    continue;
+                };
+                // Checked before recording, so the cap holds even at zero
+                if total >= max_results {
+                    return false;
+                }
+                batch.push(SearchMatch {
+                    path: path.to_string_lossy().to_string(),
+                    line: index + 1,
+                    column,
+                    text: line.chars().take(MAX_LINE_PREVIEW).collect(),
+                });
+                total += 1;
+            }
+
+            if batch.len() >= MAX_RESULT_BATCH || last_emit.elapsed() >= RESULT_EMIT_INTERVAL {
+                emit_batch(&mut batch);
+                last_emit = Instant::now();
+            }
+            true
+        },
+    )?;
+    emit_batch(&mut batch);
+
+    let done = SearchDone {
+        operation_id: operation_id.clone(),
+        files_searched,
+        matches: total,
+        truncated: !completed,
+    };
+    let _ = app_handle.emit("search-done", done.clone());
+    Ok(done)
+}
+
+/// Finds every occurrence of `pattern` in one file, for the editor's find
+/// widget. `pattern` is literal text unless `regex` is set; search is
+/// case-insensitive unless `case_sensitive` is set. Empty matches are
+/// skipped, and at most `max_results` (default 10,000) are returned. Unlike
+/// `search_in_files` there's no size limit and binary files aren't skipped.
+#[tauri::command(async)]
+pub fn search_in_file(
+    path: String,
+    pattern: String,
+    regex: Option<bool>,
+    case_sensitive: Option<bool>,
+    max_results: Option<usize>,
+) -> Result<FileSearchResults, String> {
+    let matcher = build_matcher(&pattern, regex.unwrap_or(false), case_sensitive)?;
+    let max_results = max_results.unwrap_or(DEFAULT_MAX_RESULTS);
+
+    let bytes = fs::read(&path).map_err(|e| format!("Failed to read file: {}", e))?;
+
+    // Matches come in order, so line and column are tracked by scanning
+    // forward from the previous match instead of from the start each time
+    let mut matches = Vec::new();
+    let mut truncated = false;
+    let mut scanned = 0;
+    let mut line = 1;
+    // Characters between the start of the line and `scanned`
+    let mut column = 0;
+    for found in matcher.find_iter(&bytes) {
+        if found.is_empty() {
+            continue;
+        }
+        if matches.len() >= max_results {
+            truncated = true;
+            break;
+        }
+        for byte in &bytes[scanned..found.start()] {
+            if *byte == b'\n' {
+                line += 1;
+                column = 0;
+            } else if !is_continuation(*byte) {
+                column += 1;
+            }
+        }
+        scanned = found.start();
+        matches.push(FileMatch {
+            start: found.start(),
+            end: found.end(),
+            line,
+            column: column + 1,
+        });
+    }
+
+    Ok(FileSearchResults { matches, truncated })
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ReplaceProgress {
+    pub operation_id: String,
+    pub files_searched: usize,
+    pub files_changed: usize,
+    pub replacements: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileReplacements {
+    pub path: String,
+    pub replacements: usize,
+    /// Set when the file matched but couldn't be written
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReplaceResults {
+    pub files_searched: usize,
+    /// Only files with at least one match
+    pub files: Vec<FileReplacements>,
+    pub replacements: usize,
+    pub dry_run: bool,
+}
+
+/// Replaces every occurrence of `pattern` in the text files below `root`,
+/// skipping the same files as `search_in_files`. A regex `replacement` can
+/// refer to groups as `$1` or `${name}`; a literal one is inserted as is.
+/// Each changed file is written atomically (a symlinked one through its
+/// target, once however many links reach it), and one that fails to write is
+/// reported in its entry without stopping the rest. With `dry_run` nothing
+/// is written and the counts say what would change. Emits
+/// `replace-progress` along the way; on `cancel_operation` the walk stops
+/// with an error, and files already written stay written.
+#[tauri::command(async)]
+#[allow(clippy::too_many_arguments)]
+pub fn replace_in_files(
+    app_handle: AppHandle,
+    operations: tauri::State<'_, OperationState>,
+    config: tauri::State<'_, FsConfigState>,
+    locks: tauri::State<'_, FileLockState>,
+    root: String,
+    pattern: String,
+    replacement: String,
+    regex: Option<bool>,
+    case_sensitive: Option<bool>,
+    dry_run: Option<bool>,
+    follow_symlinks: Option<bool>,
+    operation_id: String,
+) -> Result<ReplaceResults, String> {
+    let operation = operations.start(&operation_id)?;
+    let root_path = Path::new(&root);
+    if !root_path.is_dir() {
+        return Err(format!("Path is not a directory: {}", root));
+    }
+    let regex = regex.unwrap_or(false);
+    let replacement = Replacement {
+        matcher: build_matcher(&pattern, regex, case_sensitive)?,
+        text: replacement,
+        expand: regex,
+    };
+    let dry_run = dry_run.unwrap_or(false);
+
+    let mut progress = ReplaceProgress {
+        operation_id,
+        files_searched: 0,
+        files_changed: 0,
+        replacements: 0,
+    };
+    let mut last_emit = Instant::now();
+    let files = replace_tree(
+        root_path,
+        &config.for_walk(follow_symlinks),
+        &operation.token(),
+        &replacement,
+        (!dry_run).then_some(&*locks),
+        &mut progress,
+        &mut |progress| {
+            if last_emit.elapsed() >= RESULT_EMIT_INTERVAL {
+                last_emit = Instant::now();
+                let _ = app_handle.emit("replace-progress", progress.clone());
+            }
+        },
+    )?;
+    let _ = app_handle.emit("replace-progress", progress.clone());
+
+    Ok(ReplaceResults {
+        files_searched: progress.files_searched,
+        files,
+        replacements: progress.replacements,
+        dry_run,
+    })
+}
+
+struct Replacement {
+    matcher: Regex,
+    text: String,
+    // Whether `$1`-style group references in `text` are expanded
+    expand: bool,
+}
+
+fn replace_tree(
+    root: &Path,
+    config: &FsConfig,
+    token: &CancelToken,
+    replacement: &Replacement,
+    // Checked before each write; a dry run has none and writes nothing
+    write: Option<&FileLockState>,
+    progress: &mut ReplaceProgress,
+    on_progress: &mut dyn FnMut(&ReplaceProgress),
+) -> Result<Vec<FileReplacements>, String> {
+    let mut files = Vec::new();
+    // With links followed, the same file can be reached under more than one
+    // name; it's rewritten once, through its real path so a symlink isn't
+    // replaced by a regular file
+    let mut handled = HashSet::new();
+
+    walk_files(root, config, token, &mut |path, metadata| {
+        if metadata.len() > MAX_SEARCH_FILE_SIZE {
+            return true;
+        }
+        let Ok(real_path) = fs::canonicalize(path) else {
+            return true;
+        };
+        if !handled.insert(real_path.clone()) {
+            return true;
+        }
+        let Ok(bytes) = fs::read(&real_path) else {
+            return true;
+        };
+        if looks_binary(&bytes) {
+            return true;
+        }
+        progress.files_searched += 1;
+
+        let matcher = &replacement.matcher;
+        let count = matcher.find_iter(&bytes).count();
+        if count > 0 {
+            let mut error = None;
+            if let Some(locks) = write {
+                let text = replacement.text.as_bytes();
+                let replaced = if replacement.expand {
+                    matcher.replace_all(&bytes, text)
+                } else {
+                    matcher.replace_all(&bytes, NoExpand(text))
+                };
+                error = locks
+                    .ensure_unlocked(&real_path)
+                    .and_then(|_| write_atomic(&real_path, &replaced, false))
+                    .err();
+            }
+            if error.is_none() {
+                progress.files_changed += 1;
+                progress.replacements += count;
+            }
+            files.push(FileReplacements {
+                path: path.to_string_lossy().to_string(),
+                replacements: count,
+                error,
+            });
+        }
+
+        on_progress(progress);
+        true
+    })?;
+
+    Ok(files)
+}
+
+// A literal pattern is escaped so it can go through the same regex engine.
+// Multi-line mode makes ^ and $ match at every line, as in an editor
+fn build_matcher(
+    pattern: &str,
+    regex: bool,
+    case_sensitive: Option<bool>,
+) -> Result<Regex, String> {
+    if pattern.is_empty() {
+        return Err("Search pattern must not be empty".to_string());
+    }
+    let source = if regex {
+        pattern.to_string()
+    } else {
+        regex::escape(pattern)
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(!case_sensitive.unwrap_or(false))
+        .multi_line(true)
+        .build()
+        .map_err(|e| format!("Invalid search pattern: {}", e))
+}
+
+// 1-based, counted in the original line, since case folding can change
+// byte lengths
+fn find_column(line: &str, matcher: &Regex) -> Option<usize> {
+    let found = matcher.find(line.as_bytes())?;
+    Some(char_count(&line.as_bytes()[..found.start()]) + 1)
+}
+
+// Counts everything but UTF-8 continuation bytes
+fn char_count(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|byte| !is_continuation(**byte)).count()
+}
+
+fn is_continuation(byte: u8) -> bool {
+    (byte & 0xC0) == 0x80
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::test_util::TempDir;
+
+    #[test]
+    fn search_in_file_tracks_line_and_column_across_matches() {
+        let dir = TempDir::new("file-columns");
+        let path = dir.join("text.txt");
+        fs::write(&path, "aé a\nxa").unwrap();
+
+        let results = search_in_file(
+            path.to_string_lossy().to_string(),
+            "a".into(),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        let positions: Vec<_> = results.matches.iter().map(|m| (m.line, m.column)).collect();
+        assert_eq!(positions, [(1, 1), (1, 4), (2, 2)]);
+    }
+
+    #[test]
+    fn find_column_counts_in_the_original_line() {
+        // 'İ' lowercases to two chars, which used to push the column right
+        let matcher = build_matcher("AB", false, Some(false)).unwrap();
+        assert_eq!(find_column("İab", &matcher), Some(2));
+        assert_eq!(find_column("xyz", &matcher), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn replace_tree_writes_through_symlinked_files_once() {
+        let dir = TempDir::new("replace-links");
+        fs::write(dir.join("real.txt"), "foo foo").unwrap();
+        std::os::unix::fs::symlink(dir.join("real.txt"), dir.join("link.txt")).unwrap();
+
+        let operations = OperationState::new();
+        let operation = operations.start("test").unwrap();
+        let config = FsConfig {
+            follow_symlinks: true,
+            ..FsConfig::default()
+        };
+        let replacement = Replacement {
+            matcher: build_matcher("foo", false, None).unwrap(),
+            text: "bar".to_string(),
+            expand: false,
+        };
+        let mut progress = ReplaceProgress {
+            operation_id: "test".to_string(),
+            files_searched: 0,
+            files_changed: 0,
+            replacements: 0,
+        };
+
+        let files = replace_tree(
+            &dir,
+            &config,
+            &operation.token(),
+            &replacement,
+            Some(&FileLockState::new()),
+            &mut progress,
+            &mut |_| {},
+        )
+        .unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(progress.replacements, 2);
+        assert!(fs::symlink_metadata(dir.join("link.txt"))
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(fs::read_to_string(dir.join("real.txt")).unwrap(), "bar bar");
+    }
+}
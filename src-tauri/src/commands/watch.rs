@@ -0,0 +1,349 @@
+use notify::event::{EventKind, ModifyKind};
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use super::fs::{EntryCountState, FsConfig, FsConfigState};
+use super::git::git_dirs;
+
+// A rebase or checkout rewrites HEAD, the index and refs many times over;
+// `git-changed` waits until they've been quiet this long
+const GIT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Live `watch_paths` and `watch_git` handles. Dropping a watcher stops it,
+/// so taking it out of the map and stopping it is all `unwatch` needs to do.
+pub struct WatchState {
+    watchers: Mutex<HashMap<String, ActiveWatch>>,
+    // Every callback holds a read lock while it runs, so `shutdown` can wait
+    // out one that is mid-emit by taking the write lock
+    callbacks: Arc<RwLock<()>>,
+}
+
+struct ActiveWatch {
+    watcher: RecommendedWatcher,
+    // Checked by the callback, since the watcher's thread may still hand it
+    // an event it had already read when the watcher was dropped
+    stopped: Arc<AtomicBool>,
+}
+
+impl ActiveWatch {
+    fn stop(self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        drop(self.watcher);
+    }
+}
+
+impl WatchState {
+    pub fn new() -> Self {
+        Self {
+            watchers: Mutex::new(HashMap::new()),
+            callbacks: Arc::new(RwLock::new(())),
+        }
+    }
+
+    /// Stops every watcher and waits for any callback still running, so
+    /// nothing emits once it returns. Returns how many were stopped; calling
+    /// it again is harmless.
+    pub fn shutdown(&self) -> usize {
+        // Taken out first so the map isn't locked while waiting
+        let watchers = std::mem::take(&mut *self.watchers.lock());
+        let stopped = watchers.len();
+        for (_, watch) in watchers {
+            watch.stop();
+        }
+        drop(self.callbacks.write());
+        stopped
+    }
+}
+
+impl Default for WatchState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Create,
+    Modify,
+    Rename,
+    Remove,
+    Other,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FsChange {
+    pub watch_id: String,
+    /// The watched path, as passed to `watch_paths`, that the change is under
+    pub root: String,
+    pub kind: ChangeKind,
+    pub paths: Vec<String>,
+}
+
+// A watched path as given, and canonicalized to match event paths against
+// (FSEvents reports /private/var/... for /var/..., for example)
+struct Root {
+    given: String,
+    canonical: PathBuf,
+}
+
+/// Watches every path in `paths` (recursively unless `recursive` is false)
+/// with a single watcher and returns its ID for `unwatch`. Each change is
+/// emitted as `fs-change` with the root it belongs to. Changes below names
+/// ignored by the `FsConfig` at the time of the call are dropped. Every
+/// change also drops the affected folders' cached entry counts.
+#[tauri::command]
+pub fn watch_paths(
+    app_handle: AppHandle,
+    state: tauri::State<'_, WatchState>,
+    config: tauri::State<'_, FsConfigState>,
+    counts: tauri::State<'_, EntryCountState>,
+    paths: Vec<String>,
+    recursive: Option<bool>,
+) -> Result<String, String> {
+    if paths.is_empty() {
+        return Err("No paths to watch".to_string());
+    }
+
+    let roots = paths
+        .iter()
+        .map(|path| {
+            Path::new(path)
+                .canonicalize()
+                .map(|canonical| Root {
+                    given: path.clone(),
+                    canonical,
+                })
+                .map_err(|e| format!("Failed to watch {}: {}", path, e))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    let watch_id = Uuid::new_v4().to_string();
+    let handler_id = watch_id.clone();
+    let handler_roots: Vec<(String, PathBuf)> = roots
+        .iter()
+        .map(|root| (root.given.clone(), root.canonical.clone()))
+        .collect();
+    let fs_config = config.get();
+    let counts = counts.inner().clone();
+    let stopped = Arc::new(AtomicBool::new(false));
+    let handler_stopped = stopped.clone();
+    let callbacks = state.callbacks.clone();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        let _running = callbacks.read();
+        if handler_stopped.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Ok(event) = event {
+            for path in &event.paths {
+                counts.invalidate(path);
+            }
+            emit_change(&app_handle, &handler_id, &handler_roots, &fs_config, event);
+        }
+    })
+    .map_err(|e| format!("Failed to create watcher: {}", e))?;
+
+    let mode = if recursive.unwrap_or(true) {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    for root in &roots {
+        watcher
+            .watch(&root.canonical, mode)
+            .map_err(|e| format!("Failed to watch {}: {}", root.given, e))?;
+    }
+
+    state
+        .watchers
+        .lock()
+        .insert(watch_id.clone(), ActiveWatch { watcher, stopped });
+    Ok(watch_id)
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct GitChanged {
+    pub watch_id: String,
+    /// The path passed to `watch_git`
+    pub root: String,
+}
+
+/// Watches the HEAD, index and refs of the repository containing `path` and
+/// emits `git-changed` once they settle after a change, so branch and status
+/// can be refreshed after a commit, checkout or fetch made outside the app.
+/// Returns an ID for `unwatch`, shared with `watch_paths` handles.
+#[tauri::command]
+pub fn watch_git(
+    app_handle: AppHandle,
+    state: tauri::State<'_, WatchState>,
+    path: String,
+) -> Result<String, String> {
+    let (git_dir, common_dir) = git_dirs(&path)?;
+    let refs_dir = common_dir.join("refs");
+    let refs_canonical = refs_dir
+        .canonicalize()
+        .map_err(|e| format!("Failed to watch {}: {}", refs_dir.display(), e))?;
+    // Backends differ on whether events carry the watched or the resolved
+    // path; inotify reports the watched one
+    let refs_dirs = [refs_dir.clone(), refs_canonical];
+
+    let watch_id = Uuid::new_v4().to_string();
+    let stopped = Arc::new(AtomicBool::new(false));
+    let handler_stopped = stopped.clone();
+    let callbacks = state.callbacks.clone();
+    let (changes, pending) = mpsc::channel();
+
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if let Ok(event) = event {
+            if !matches!(event.kind, EventKind::Access(_))
+                && event
+                    .paths
+                    .iter()
+                    .any(|path| is_git_state(path, &refs_dirs))
+            {
+                let _ = changes.send(());
+            }
+        }
+    })
+    .map_err(|e| format!("Failed to create watcher: {}", e))?;
+
+    let mut dirs = vec![(&git_dir, RecursiveMode::NonRecursive)];
+    if common_dir != git_dir {
+        dirs.push((&common_dir, RecursiveMode::NonRecursive));
+    }
+    dirs.push((&refs_dir, RecursiveMode::Recursive));
+    for (dir, mode) in dirs {
+        watcher
+            .watch(dir, mode)
+            .map_err(|e| format!("Failed to watch {}: {}", dir.display(), e))?;
+    }
+
+    // Ends once the watcher, and with it the sender, is dropped
+    let handler_id = watch_id.clone();
+    thread::spawn(move || {
+        while pending.recv().is_ok() {
+            loop {
+                match pending.recv_timeout(GIT_DEBOUNCE) {
+                    Ok(()) => continue,
+                    Err(mpsc::RecvTimeoutError::Timeout) => break,
+                    Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                }
+            }
+            let _running = callbacks.read();
+            if handler_stopped.load(Ordering::Relaxed) {
+                return;
+            }
+            let _ = app_handle.emit(
+                "git-changed",
+                GitChanged {
+                    watch_id: handler_id.clone(),
+                    root: path.clone(),
+                },
+            );
+        }
+    });
+
+    state
+        .watchers
+        .lock()
+        .insert(watch_id.clone(), ActiveWatch { watcher, stopped });
+    Ok(watch_id)
+}
+
+// Lock files come and go around every write; the rename onto the real name
+// is the change that matters
+fn is_git_state(path: &Path, refs_dirs: &[PathBuf]) -> bool {
+    let Some(name) = path.file_name().map(|name| name.to_string_lossy()) else {
+        return false;
+    };
+    if name.ends_with(".lock") {
+        return false;
+    }
+    matches!(name.as_ref(), "HEAD" | "index" | "packed-refs")
+        || refs_dirs.iter().any(|dir| path.starts_with(dir))
+}
+
+/// Stops every path registered under the `watch_paths` or `watch_git`
+/// handle.
+#[tauri::command]
+pub fn unwatch(state: tauri::State<'_, WatchState>, watch_id: String) -> Result<(), String> {
+    state
+        .watchers
+        .lock()
+        .remove(&watch_id)
+        .map(ActiveWatch::stop)
+        .ok_or_else(|| format!("Watch not found: {}", watch_id))
+}
+
+/// Stops all watchers, as on app exit. Returns how many were running.
+#[tauri::command]
+pub fn shutdown_watchers(state: tauri::State<'_, WatchState>) -> usize {
+    state.shutdown()
+}
+
+fn emit_change(
+    app_handle: &AppHandle,
+    watch_id: &str,
+    roots: &[(String, PathBuf)],
+    config: &FsConfig,
+    event: Event,
+) {
+    let kind = match event.kind {
+        EventKind::Create(_) => ChangeKind::Create,
+        EventKind::Modify(ModifyKind::Name(_)) => ChangeKind::Rename,
+        EventKind::Modify(_) => ChangeKind::Modify,
+        EventKind::Remove(_) => ChangeKind::Remove,
+        // Reads and opens don't change anything
+        EventKind::Access(_) => return,
+        EventKind::Any | EventKind::Other => ChangeKind::Other,
+    };
+
+    // Nested roots: the change belongs to the innermost one
+    let mut by_root: HashMap<&str, Vec<String>> = HashMap::new();
+    for path in &event.paths {
+        let Some((given, canonical)) = roots
+            .iter()
+            .filter(|(_, canonical)| path.starts_with(canonical))
+            .max_by_key(|(_, canonical)| canonical.components().count())
+        else {
+            continue;
+        };
+
+        let ignored = path
+            .strip_prefix(canonical)
+            .map(|relative| {
+                relative
+                    .iter()
+                    .any(|name| config.is_ignored(&name.to_string_lossy()))
+            })
+            .unwrap_or(false);
+        if !ignored {
+            by_root
+                .entry(given.as_str())
+                .or_default()
+                .push(path.to_string_lossy().to_string());
+        }
+    }
+
+    for (root, paths) in by_root {
+        let _ = app_handle.emit(
+            "fs-change",
+            FsChange {
+                watch_id: watch_id.to_string(),
+                root: root.to_string(),
+                kind,
+                paths,
+            },
+        );
+    }
+}
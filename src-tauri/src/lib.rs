@@ -1,27 +1,191 @@
 mod commands;
 
-use commands::fs::{get_file_name, read_directory, read_file, write_file};
-use commands::pty::{kill_pty, resize_pty, spawn_pty, write_to_pty, PtyState};
+use commands::archive::{create_archive, extract_archive, extract_archive_entries, list_archive};
+use commands::diff::{apply_patch, diff_files};
+use commands::fs::{
+    cleanup_temp_files, count_directory_entries, count_file_lines, create_symlink,
+    create_temp_file, detect_indentation, disk_space, expand_path, file_matches_content,
+    file_mime_type, files_changed_since, get_file_name, get_fs_config, get_permissions, image_info,
+    is_binary_file, is_path_writable, lock_file, normalize_path_set, open_path_external,
+    preview_files, read_directory, read_directory_paged, read_file, read_file_auto,
+    read_file_detailed, read_file_from_line, read_file_split, read_file_stable,
+    read_file_with_hash, read_file_with_language, read_symlink_target, recent_files, relative_path,
+    rename_path_unique, resolve_symlink, set_fs_config, set_permissions, set_workspace_roots,
+    stat_paths, stream_file, truncate_file, unlock_file, write_file, write_file_atomic,
+    write_file_from_template, write_file_new, write_file_with_backup, write_files_batch,
+    EntryCountState, FileLockState, FsConfigState, TempFileState, WorkspaceRootsState,
+};
+use commands::git::{
+    find_git_root, git_blame, git_branches, git_file_diff, git_show_file, git_status,
+};
+use commands::operation::{cancel_operation, OperationState};
+use commands::process::{
+    get_pty_foreground, get_pty_process_tree, get_pty_resource_usage, processes_using_file,
+    reap_orphans, run_command,
+};
+use commands::projects::{
+    add_recent_project, clear_recent_projects, detect_project_type, get_recent_projects,
+    read_config_resolved,
+};
+use commands::pty::{
+    attach_multiplexer, export_pty_env, flush_pty_output, get_environment, get_pty_diagnostics,
+    get_pty_mouse_mode, get_pty_screen, get_pty_scrollback, get_pty_scrollback_capacity,
+    get_pty_size, kill_pty, list_ptys, pipe_ptys, reset_pty, resize_pty, respawn_pty_in,
+    restore_ptys, set_active_pty, set_pty_label, set_pty_mode, set_pty_mouse_mode,
+    set_pty_scrollback_capacity, snapshot_ptys, spawn_pty, unpipe_pty, unwatch_pty_prompt,
+    watch_pty_prompt, which_command, write_to_pty, write_to_pty_paced, PtyState,
+};
+use commands::search::{replace_in_files, search_in_file, search_in_files};
+use commands::watch::{shutdown_watchers, unwatch, watch_git, watch_paths, WatchState};
+use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    #[cfg(unix)]
+    commands::pty::run_exec_shim();
+
     tauri::Builder::default()
         .manage(PtyState::new())
+        .manage(OperationState::new())
+        .manage(FsConfigState::new())
+        .manage(WatchState::new())
+        .manage(EntryCountState::new())
+        .manage(TempFileState::new())
+        .manage(FileLockState::new())
+        .manage(WorkspaceRootsState::new())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        // Watcher threads would otherwise keep emitting into a window
+        // that's being torn down, and temp files would outlive the session
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                window.state::<WatchState>().shutdown();
+                window.state::<TempFileState>().cleanup();
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             // File system commands
             read_directory,
+            read_directory_paged,
+            count_directory_entries,
             read_file,
+            read_file_detailed,
+            read_file_auto,
+            read_file_stable,
+            read_file_with_language,
+            read_file_with_hash,
+            read_file_from_line,
+            read_file_split,
+            stream_file,
+            count_file_lines,
+            detect_indentation,
             write_file,
+            write_file_atomic,
+            write_file_new,
+            write_file_with_backup,
+            write_file_from_template,
+            write_files_batch,
+            create_temp_file,
+            cleanup_temp_files,
+            truncate_file,
+            lock_file,
+            unlock_file,
+            rename_path_unique,
+            create_symlink,
+            read_symlink_target,
+            resolve_symlink,
             get_file_name,
+            stat_paths,
+            preview_files,
+            recent_files,
+            files_changed_since,
+            is_path_writable,
+            get_permissions,
+            set_permissions,
+            is_binary_file,
+            file_mime_type,
+            file_matches_content,
+            image_info,
+            expand_path,
+            normalize_path_set,
+            relative_path,
+            disk_space,
+            open_path_external,
+            set_workspace_roots,
+            get_fs_config,
+            set_fs_config,
+            // Search commands
+            search_in_files,
+            search_in_file,
+            replace_in_files,
+            // Diff commands
+            diff_files,
+            apply_patch,
+            // Git commands
+            git_status,
+            find_git_root,
+            git_blame,
+            git_file_diff,
+            git_branches,
+            git_show_file,
+            // Watch commands
+            watch_paths,
+            watch_git,
+            unwatch,
+            shutdown_watchers,
+            // Archive commands
+            create_archive,
+            extract_archive,
+            list_archive,
+            extract_archive_entries,
+            // Project commands
+            add_recent_project,
+            get_recent_projects,
+            clear_recent_projects,
+            detect_project_type,
+            read_config_resolved,
+            // Operation commands
+            cancel_operation,
             // PTY commands
             spawn_pty,
             write_to_pty,
+            write_to_pty_paced,
+            flush_pty_output,
+            set_active_pty,
+            reset_pty,
             resize_pty,
+            get_pty_size,
+            get_pty_screen,
+            get_pty_scrollback,
+            get_pty_scrollback_capacity,
+            set_pty_scrollback_capacity,
+            get_pty_diagnostics,
+            list_ptys,
+            set_pty_label,
+            set_pty_mode,
+            get_pty_mouse_mode,
+            set_pty_mouse_mode,
+            watch_pty_prompt,
+            unwatch_pty_prompt,
             kill_pty,
+            respawn_pty_in,
+            pipe_ptys,
+            unpipe_pty,
+            snapshot_ptys,
+            restore_ptys,
+            attach_multiplexer,
+            which_command,
+            get_environment,
+            export_pty_env,
+            // Process commands
+            get_pty_process_tree,
+            get_pty_foreground,
+            get_pty_resource_usage,
+            reap_orphans,
+            run_command,
+            processes_using_file,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
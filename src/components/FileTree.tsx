@@ -1,12 +1,13 @@
 import { useState, useEffect, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { Workspace, useWorkspaceStore, getLanguageFromPath } from '../store/workspace';
+import { Workspace, useWorkspaceStore } from '../store/workspace';
 
 interface FileEntry {
   name: string;
   path: string;
   is_dir: boolean;
   is_hidden: boolean;
+  kind: 'file' | 'dir' | 'fifo' | 'socket' | 'char_device' | 'block_device' | 'other';
 }
 
 interface FileTreeProps {
@@ -85,15 +86,18 @@ function TreeNode({ entry, level, workspaceId }: TreeNodeProps) {
         await loadChildren();
       }
       setIsExpanded(!isExpanded);
-    } else {
+    } else if (entry.kind === 'file') {
       try {
-        const content = await invoke<string>('read_file', { path: entry.path });
+        const { content, language } = await invoke<{ content: string; language: string }>(
+          'read_file_with_language',
+          { path: entry.path }
+        );
         openFile(workspaceId, {
           id: entry.path,
           path: entry.path,
           name: entry.name,
           content,
-          language: getLanguageFromPath(entry.path),
+          language,
         });
       } catch (err) {
         console.error('Failed to read file:', err);
@@ -44,6 +44,7 @@ export function WorkspaceTabs() {
     if (selected && typeof selected === 'string') {
       const name = await invoke<string>('get_file_name', { path: selected });
       addWorkspace(selected, name);
+      invoke('add_recent_project', { path: selected }).catch(console.error);
     }
   }
 
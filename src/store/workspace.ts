@@ -67,45 +67,6 @@ function generateId(): string {
   return Math.random().toString(36).substring(2, 11);
 }
 
-function getLanguageFromPath(path: string): string {
-  const ext = path.split('.').pop()?.toLowerCase() || '';
-  const languageMap: Record<string, string> = {
-    'ts': 'typescript',
-    'tsx': 'typescript',
-    'js': 'javascript',
-    'jsx': 'javascript',
-    'json': 'json',
-    'html': 'html',
-    'css': 'css',
-    'scss': 'scss',
-    'less': 'less',
-    'md': 'markdown',
-    'py': 'python',
-    'rs': 'rust',
-    'go': 'go',
-    'java': 'java',
-    'c': 'c',
-    'cpp': 'cpp',
-    'h': 'c',
-    'hpp': 'cpp',
-    'rb': 'ruby',
-    'php': 'php',
-    'swift': 'swift',
-    'kt': 'kotlin',
-    'sql': 'sql',
-    'yaml': 'yaml',
-    'yml': 'yaml',
-    'xml': 'xml',
-    'sh': 'shell',
-    'bash': 'shell',
-    'zsh': 'shell',
-    'toml': 'toml',
-    'ini': 'ini',
-    'env': 'plaintext',
-  };
-  return languageMap[ext] || 'plaintext';
-}
-
 export const useWorkspaceStore = create<WorkspaceState>((set) => ({
   workspaces: [],
   activeWorkspaceId: null,
@@ -269,4 +230,3 @@ export const useActiveFile = () => {
   return workspace.openFiles.find((f) => f.id === workspace.activeFileId) || null;
 };
 
-export { getLanguageFromPath };